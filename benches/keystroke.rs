@@ -0,0 +1,97 @@
+//! The cost of typing a single character in files of growing size.
+//!
+//! Every keystroke runs through the editor and its callbacks updating the global state, just
+//! like in the app. It should take about the same time regardless of the size of the file.
+//! Each one is undone again without being measured, so the edited line doesn't grow.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use cursive::{
+    event::Event,
+    view::{Nameable, View},
+    views::{LinearLayout, Panel},
+    Cursive, Vec2,
+};
+use omega::{
+    app::{new_edit_area, FileData, State},
+    buffer::Buffer,
+    theme,
+    ui::edit_area::{Cursor, EditArea},
+};
+use ropey::Rope;
+
+/// Lines of the benchmarked files
+const SIZES: [usize; 4] = [1_000, 10_000, 50_000, 100_000];
+
+/// A file of `lines` lines of code
+fn content(lines: usize) -> String {
+    (0..lines)
+        .map(|i| format!("    let value_{i} = compute(\"{i}\", {i}); // some comment\n"))
+        .collect()
+}
+
+/// The app with `content` opened in the editor, the cursor in the middle of it
+fn setup(content: &str) -> Cursive {
+    let mut siv = Cursive::new();
+    let path = PathBuf::from("bench.rs");
+    let file = FileData {
+        buffer: Buffer::shared(content),
+        ..Default::default()
+    };
+
+    let theme = theme::themes().themes[theme::DEFAULT_THEME].clone();
+    let mut edit_area = new_edit_area(&theme);
+    edit_area.set_buffer(file.buffer.clone());
+    let rope = Rope::from_str(content);
+    edit_area.set_cursor(Cursor::from_char_offset(&rope, rope.len_chars() / 2, 4));
+    edit_area.layout(Vec2::new(120, 40));
+
+    let layout = LinearLayout::vertical().child(edit_area.with_name("editor"));
+    siv.add_layer(Panel::new(layout).with_name("editor_title"));
+
+    let mut state = State {
+        current_file: Some(path.clone()),
+        ..Default::default()
+    };
+    state.files.insert(path, file);
+    siv.set_user_data(state);
+    siv
+}
+
+/// Passes `event` to the editor and runs its callbacks
+fn send(siv: &mut Cursive, event: Event) {
+    let result = siv
+        .call_on_name("editor", |edit_area: &mut EditArea| {
+            edit_area.on_event(event)
+        })
+        .unwrap();
+    result.process(siv);
+}
+
+fn keystroke(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keystroke");
+    for lines in SIZES {
+        let mut siv = setup(&content(lines));
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &lines, |b, _| {
+            b.iter_custom(|iters| {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iters {
+                    let start = Instant::now();
+                    send(&mut siv, Event::Char('x'));
+                    elapsed += start.elapsed();
+                    // undo
+                    send(&mut siv, Event::CtrlChar('z'));
+                }
+                elapsed
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, keystroke);
+criterion_main!(benches);
@@ -1,26 +1,38 @@
 use std::{
-    collections::HashMap,
-    env,
+    collections::{hash_map::DefaultHasher, HashMap},
+    env, fs,
+    hash::{Hash, Hasher},
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 use crate::ui::{
     edit_area::{Cursor, EditArea},
-    quick_access, update_ui_state,
+    project_search, search_bar, update_current_title, update_title, update_ui_state,
+};
+use crate::{
+    buffer::SharedBuffer,
+    file_index::FileIndex,
+    history::{EditKind, Snapshot},
+    indent::Indent,
 };
 use cursive::{
-    event::{Event, Key},
+    event::Event,
     reexports::log::info,
     view::{Nameable, Resizable},
     views::{LinearLayout, NamedView, Panel, ResizedView, ScrollView},
     Vec2,
 };
 use cursive_tree_view::TreeView;
-use syntect::highlighting::ThemeSet;
+use ropey::Rope;
+use syntect::highlighting::Theme;
 
 use crate::{
+    backend, config,
     error::ResultExt,
+    session, swap, theme,
     ui::file_tree::{self, TreeEntry},
+    watcher,
 };
 
 pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
@@ -30,20 +42,98 @@ pub const PKG_AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 pub const PKG_DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
 pub const PKG_LICENSE: &str = env!("CARGO_PKG_LICENSE");
 
+/// Max amount of remembered recently opened files.
+const RECENT_LIMIT: usize = 50;
+
+/// The directory of persistent state like sessions and swap files (`~/.local/state/omega`)
+pub fn state_dir() -> Option<PathBuf> {
+    let dir = dirs::state_dir().or_else(dirs::data_local_dir)?;
+    Some(dir.join(PKG_NAME))
+}
+
+/// Encodes `path` as a single file name, used for files in the `state_dir`
+pub fn state_file_name(path: &Path) -> String {
+    path.to_string_lossy().replace(['/', '\\', ':'], "%")
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct State {
     pub project_path: PathBuf,
     pub current_file: Option<PathBuf>,
     pub files: HashMap<PathBuf, FileData>,
     pub files_edited: HashMap<PathBuf, bool>,
+    /// All files of the project for the Quick Access view
+    pub index: FileIndex,
+    /// Recently opened files, most recent first
+    pub recent_files: Vec<PathBuf>,
 }
 
 #[derive(Clone, Debug, Default)]
 
 pub struct FileData {
-    pub str: String,
+    /// The content and history, edited directly by the editor while the file is opened
+    pub buffer: SharedBuffer,
     pub scroll_offset: Vec2,
     pub cursor: Cursor,
+    /// The version on disk the buffer is based on
+    pub disk: DiskState,
+    /// Hash of a conflicting version on disk the user was already asked about
+    pub ignored_change: Option<u64>,
+    /// Language chosen via `>language`, detected if `None`
+    pub language: Option<String>,
+    /// Indentation chosen via `>indentation`, detected if `None`
+    pub indent: Option<Indent>,
+    /// Whether long lines are wrapped, toggled via `>wrap`
+    pub wrap: bool,
+}
+
+impl FileData {
+    /// The current content, sharing its nodes with the buffer
+    pub fn content(&self) -> Rope {
+        self.buffer.lock().unwrap().content.clone()
+    }
+
+    /// Replaces the content outside of the editor, the previous one can be restored via undo
+    pub fn set_content(&mut self, content: String) {
+        let mut buffer = self.buffer.lock().unwrap();
+        let snapshot = Snapshot {
+            content: buffer.content.clone(),
+            cursor: self.cursor,
+            scroll_offset: self.scroll_offset,
+        };
+        buffer.history.record(EditKind::Other, snapshot);
+        buffer.content = Rope::from_str(&content);
+        let tab_width = config::get().editor.tab_size;
+        self.cursor = Cursor::from_char_offset(&buffer.content, self.cursor.char_offset, tab_width);
+    }
+}
+
+/// A version of a file on disk
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiskState {
+    pub modified: Option<SystemTime>,
+    pub hash: u64,
+}
+
+impl DiskState {
+    /// The state of the file at `path` with the just read or written `content`
+    pub fn new(path: &Path, content: &str) -> Self {
+        Self {
+            modified: modified(path),
+            hash: content_hash(content),
+        }
+    }
+}
+
+/// The last modification time of the file at `path`
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+pub fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
 }
 
 impl State {
@@ -63,6 +153,13 @@ impl State {
         self.get_file(self.current_file.as_ref().unwrap_or(&PathBuf::default()))
     }
 
+    /// Moves `path` to the front of the recent files
+    pub fn add_recent_file(&mut self, path: PathBuf) {
+        self.recent_files.retain(|recent| recent != &path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(RECENT_LIMIT);
+    }
+
     pub fn remove(&mut self, path: &PathBuf) {
         for file_path in self.files.clone().keys() {
             if file_path.starts_with(path) {
@@ -89,12 +186,11 @@ impl State {
         self.to_owned()
     }
 
-    pub fn open_new_file(&mut self, current_file: PathBuf, content: FileData) -> Self {
+    pub fn open_new_file(&mut self, current_file: PathBuf, content: FileData) {
         let canonicalized_current_file = current_file.canonicalize().unwrap_or_default();
         self.files
             .insert(canonicalized_current_file.clone(), content);
         self.current_file = Some(canonicalized_current_file);
-        self.to_owned()
     }
 
     pub fn update_paths_after_rename(&mut self, old_parent: &Path, new_parent: &Path) {
@@ -127,9 +223,62 @@ impl State {
 }
 
 // Helper types of the main/tree panel
-pub type EditorPanel = Panel<ResizedView<NamedView<EditArea>>>;
+pub type EditorPanel = Panel<LinearLayout>;
 pub type TreePanel = ResizedView<Panel<ScrollView<NamedView<TreeView<TreeEntry>>>>>;
 
+/// Creates the editor, keeping the global state of the current file up to date
+///
+/// The content is already in the file's shared buffer, so only the cursor, the scroll offset
+/// and whether the file is edited are updated, in place without copying the state.
+pub fn new_edit_area(theme: &Theme) -> EditArea {
+    let mut edit_area = EditArea::new(theme);
+
+    // Detecting edits on `EditArea` and updating global state.
+    edit_area.set_on_edit(|siv, content, scroll_offset, cursor| {
+        let current_file = siv
+            .with_user_data(|state: &mut State| {
+                let current_file = state.current_file.clone()?;
+                let file = state.files.get_mut(&current_file)?;
+                file.scroll_offset = scroll_offset;
+                file.cursor = cursor;
+                state.files_edited.insert(current_file.clone(), true);
+                Some(current_file)
+            })
+            .flatten();
+        if let Some(current_file) = current_file {
+            swap::update(&current_file, content.clone());
+            update_title(siv, true, &current_file);
+        }
+
+        search_bar::update_count(siv);
+    });
+
+    // Detecting cursor changes and updating global state.
+    edit_area.set_on_interact(|siv, _, scroll_offset, cursor| {
+        siv.with_user_data(|state: &mut State| {
+            let current_file = state.current_file.as_ref()?;
+            let file = state.files.get_mut(current_file)?;
+            file.scroll_offset = scroll_offset;
+            file.cursor = cursor;
+            Some(())
+        });
+    });
+
+    // Showing the mode of the modal editing in the title.
+    edit_area.set_on_mode(|siv, _| update_current_title(siv));
+
+    // Detecting scrolling and updating global state.
+    edit_area.set_on_scroll(|siv, _, scroll_offset, _| {
+        siv.with_user_data(|state: &mut State| {
+            let current_file = state.current_file.as_ref()?;
+            state.files.get_mut(current_file)?.scroll_offset = scroll_offset;
+            Some(())
+        });
+    });
+
+    edit_area
+}
+
 /// Starts the app && event loop
 pub fn start() {
     let mut siv = cursive::default();
@@ -160,118 +309,24 @@ pub fn start() {
     // disable/handle global shortcuts
     siv.clear_global_callbacks(Event::CtrlChar('c'));
 
-    siv.clear_global_callbacks(Event::CtrlChar('p'));
-    siv.clear_global_callbacks(Event::Key(Key::Esc));
-
-    siv.add_global_callback(Event::CtrlChar('p'), |s| quick_access::new(s).handle(s));
-    siv.add_global_callback(Event::Key(Key::Esc), |s| {
-        if s.screen().len() > 1 {
-            s.pop_layer();
-        }
-    });
-
     // The current theme, needs to be passed on the general styling and the editor ui for fitting syntax highlighting style.
-    let theme = ThemeSet::load_defaults().themes["base16-eighties.dark"].clone();
-
-    let mut raw_edit_area = EditArea::new(&theme).disabled();
-
-    // Detecting edits on `EditArea` and updating global state.
-    raw_edit_area.set_on_edit(|siv, content, scroll_offset, cursor| {
-        let mut state = siv
-            .with_user_data(|state: &mut State| state.clone())
-            .unwrap_or_default();
-        if let Some(current_file) = &state.current_file {
-            let contents = state.files.get_mut(current_file);
-            if let Some(contents) = contents {
-                contents.str = content.to_string();
-                contents.scroll_offset = scroll_offset;
-                contents.cursor = cursor;
-                state.files_edited.insert(current_file.clone(), true);
-
-                // Update title.
-                let title = state
-                    .clone()
-                    .current_file
-                    .unwrap_or_default()
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string()
-                    + " *";
-                siv.call_on_name("editor_title", |view: &mut EditorPanel| {
-                    view.set_title(title);
-                })
-                .unwrap();
-            }
-        }
-        siv.set_user_data(state);
-    });
-
-    // Detecting cursor changes and updating global state.
-    raw_edit_area.set_on_interact(|siv, _, scroll_offset, cursor| {
-        let mut state = siv
-            .with_user_data(|state: &mut State| state.clone())
-            .unwrap_or_default();
-        if let Some(current_file) = &state.current_file {
-            let contents = state.files.get_mut(current_file);
-            if let Some(contents) = contents {
-                contents.scroll_offset = scroll_offset;
-                contents.cursor = cursor;
-            }
-        }
-        siv.set_user_data(state);
-    });
-
-    // Detecting scrolling and updating global state.
-    raw_edit_area.set_on_scroll(|siv, _, scroll_offset, _| {
-        let mut state = siv
-            .with_user_data(|state: &mut State| state.clone())
-            .unwrap_or_default();
-        if let Some(current_file) = &state.current_file {
-            let contents = state.files.get_mut(current_file);
-            if let Some(contents) = contents {
-                contents.scroll_offset = scroll_offset;
-            }
-        }
-        siv.set_user_data(state);
-    });
+    let theme = theme::themes().themes[theme::DEFAULT_THEME].clone();
 
     // Setting general styling to theme
-    siv.with_theme(|t| {
-        t.shadow = false;
-        if let Some(background) = theme
-            .settings
-            .background
-            .map(cursive_syntect::translate_color)
-        {
-            t.palette[cursive::theme::PaletteColor::Background] = background;
-            t.palette[cursive::theme::PaletteColor::View] = background;
-        }
-        if let Some(foreground) = theme
-            .settings
-            .foreground
-            .map(cursive_syntect::translate_color)
-        {
-            t.palette[cursive::theme::PaletteColor::Primary] = foreground;
-            t.palette[cursive::theme::PaletteColor::Secondary] = foreground;
-            t.palette[cursive::theme::PaletteColor::Tertiary] = foreground;
-            t.palette[cursive::theme::PaletteColor::TitlePrimary] = foreground;
-            t.palette[cursive::theme::PaletteColor::TitleSecondary] = foreground;
-        }
-
-        if let Some(highlight) = theme
-            .settings
-            .highlight
-            .map(cursive_syntect::translate_color)
-        {
-            t.palette[cursive::theme::PaletteColor::Highlight] = highlight;
-            t.palette[cursive::theme::PaletteColor::HighlightText] = highlight;
-        }
-    });
-
-    let edit_area = raw_edit_area.with_name("editor").full_screen();
-
-    let editor_panel = Panel::new(edit_area).title("").with_name("editor_title");
+    theme::apply(&mut siv, &theme);
+
+    let edit_area = new_edit_area(&theme)
+        .disabled()
+        .with_name("editor")
+        .full_screen();
+
+    let editor_layout = LinearLayout::vertical()
+        .child(edit_area)
+        .child(search_bar::new())
+        .child(project_search::new());
+    let editor_panel = Panel::new(editor_layout)
+        .title("")
+        .with_name("editor_title");
     let file_tree_panel = Panel::new(file_tree::new(&project_path))
         .title("")
         .fixed_width(40)
@@ -279,15 +334,25 @@ pub fn start() {
 
     let layout = LinearLayout::horizontal()
         .child(file_tree_panel)
-        .child(editor_panel);
+        .child(editor_panel)
+        .with_name("layout");
 
     siv.add_fullscreen_layer(layout);
+    config::start(&mut siv);
 
-    // Set initial data.
+    // Set initial data, the state is borrowed in place from here on.
+    siv.set_user_data(State::default());
     update_ui_state(&mut siv, &project_path, file_path.as_ref()).unwrap();
+    session::restore(&mut siv).handle(&mut siv);
+    swap::recover(&mut siv).handle(&mut siv);
+    swap::start();
+    watcher::start(&mut siv);
 
     info!("App up and running. Initial setup finished!");
 
-    // Start event loop.
-    siv.run();
+    // Start event loop, with a backend keeping the modifiers of mouse events.
+    siv.try_run_with(backend::Backend::init).unwrap();
+
+    // Write out the remaining swap file changes, e.g. removals after saving on quit.
+    swap::flush();
 }
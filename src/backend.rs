@@ -0,0 +1,199 @@
+//! The crossterm backend of cursive, keeping the `Alt` modifier of mouse events.
+//!
+//! Cursive drops the modifiers of mouse events, so the input is read and translated here, while
+//! the output is left to its backend. Whether `Alt` was held is kept for alt-clicking, see
+//! [`alt_held`].
+//!
+//! Crossterm events can't be peeked at or put back, so only reading the mouse events here isn't
+//! possible. The translation mirrors `map_key`, `translate_event` and `translate_key` of the
+//! crossterm backend of cursive 0.21.1 (`src/backends/crossterm.rs`) and has to be compared
+//! with it when upgrading cursive. It only differs in keeping the `Alt` modifier of mouse events,
+//! ignoring pastes and quitting on read errors instead of panicking.
+
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use crossterm::event::{
+    self, Event as CEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton as CButton,
+    MouseEventKind,
+};
+use cursive::{
+    backend,
+    event::{Event, Key, MouseButton, MouseEvent},
+    reexports::log::error,
+    theme::{Color, ColorPair, Effect},
+    Vec2,
+};
+
+/// Whether `Alt` was held during the last mouse event
+static ALT_HELD: AtomicBool = AtomicBool::new(false);
+
+/// Whether `Alt` was held during the last mouse event, e.g. for an alt-click
+pub fn alt_held() -> bool {
+    ALT_HELD.load(Ordering::Relaxed)
+}
+
+pub struct Backend {
+    /// Cursive's crossterm backend, used for everything but the input
+    inner: Box<dyn backend::Backend>,
+}
+
+impl Backend {
+    /// Creates the backend, setting up the terminal
+    pub fn init() -> io::Result<Box<dyn backend::Backend>> {
+        Ok(Box::new(Self {
+            inner: cursive::backends::crossterm::Backend::init()?,
+        }))
+    }
+}
+
+impl backend::Backend for Backend {
+    fn poll_event(&mut self) -> Option<Event> {
+        while event::poll(Duration::from_millis(1)).ok()? {
+            match event::read() {
+                Ok(event) => {
+                    if let Some(event) = translate_event(event) {
+                        return Some(event);
+                    }
+                }
+                Err(e) => {
+                    error!("Reading the input failed: {e}");
+                    return Some(Event::Exit);
+                }
+            }
+        }
+        None
+    }
+
+    fn set_title(&mut self, title: String) {
+        self.inner.set_title(title);
+    }
+
+    fn refresh(&mut self) {
+        self.inner.refresh();
+    }
+
+    fn has_colors(&self) -> bool {
+        self.inner.has_colors()
+    }
+
+    fn screen_size(&self) -> Vec2 {
+        self.inner.screen_size()
+    }
+
+    fn move_to(&self, pos: Vec2) {
+        self.inner.move_to(pos);
+    }
+
+    fn print(&self, text: &str) {
+        self.inner.print(text);
+    }
+
+    fn clear(&self, color: Color) {
+        self.inner.clear(color);
+    }
+
+    fn set_color(&self, colors: ColorPair) -> ColorPair {
+        self.inner.set_color(colors)
+    }
+
+    fn set_effect(&self, effect: Effect) {
+        self.inner.set_effect(effect);
+    }
+
+    fn is_persistent(&self) -> bool {
+        self.inner.is_persistent()
+    }
+
+    fn unset_effect(&self, effect: Effect) {
+        self.inner.unset_effect(effect);
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+/// Translates a crossterm event, `None` if cursive has no equivalent, like cursive's `map_key`
+fn translate_event(event: CEvent) -> Option<Event> {
+    Some(match event {
+        CEvent::Key(event) => translate_key_event(event)?,
+        CEvent::Mouse(event) => {
+            let button = |button| match button {
+                CButton::Left => MouseButton::Left,
+                CButton::Right => MouseButton::Right,
+                CButton::Middle => MouseButton::Middle,
+            };
+            let mouse_event = match event.kind {
+                MouseEventKind::Down(b) => MouseEvent::Press(button(b)),
+                MouseEventKind::Up(b) => MouseEvent::Release(button(b)),
+                MouseEventKind::Drag(b) => MouseEvent::Hold(button(b)),
+                MouseEventKind::ScrollDown => MouseEvent::WheelDown,
+                MouseEventKind::ScrollUp => MouseEvent::WheelUp,
+                MouseEventKind::Moved
+                | MouseEventKind::ScrollLeft
+                | MouseEventKind::ScrollRight => return None,
+            };
+            ALT_HELD.store(
+                event.modifiers.contains(KeyModifiers::ALT),
+                Ordering::Relaxed,
+            );
+            Event::Mouse {
+                event: mouse_event,
+                position: (event.column, event.row).into(),
+                offset: Vec2::zero(),
+            }
+        }
+        CEvent::Resize(_, _) => Event::WindowResize,
+        CEvent::FocusGained | CEvent::FocusLost | CEvent::Paste(_) => return None,
+    })
+}
+
+/// Translates a key press with its modifiers, like cursive's `translate_event`
+fn translate_key_event(event: KeyEvent) -> Option<Event> {
+    const CTRL_ALT: KeyModifiers = KeyModifiers::CONTROL.union(KeyModifiers::ALT);
+    const CTRL_SHIFT: KeyModifiers = KeyModifiers::CONTROL.union(KeyModifiers::SHIFT);
+    const ALT_SHIFT: KeyModifiers = KeyModifiers::ALT.union(KeyModifiers::SHIFT);
+
+    if event.kind != KeyEventKind::Press {
+        return None;
+    }
+    Some(match (event.modifiers, event.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char(c)) => Event::CtrlChar(c),
+        (KeyModifiers::ALT, KeyCode::Char(c)) => Event::AltChar(c),
+        (_, KeyCode::Char(c)) => Event::Char(c),
+        // crossterm doesn't send `Shift` with the back tab
+        (_, KeyCode::BackTab) => Event::Shift(Key::Tab),
+        (CTRL_ALT, code) => Event::CtrlAlt(translate_key(code)?),
+        (CTRL_SHIFT, code) => Event::CtrlShift(translate_key(code)?),
+        (ALT_SHIFT, code) => Event::AltShift(translate_key(code)?),
+        (KeyModifiers::CONTROL, code) => Event::Ctrl(translate_key(code)?),
+        (KeyModifiers::ALT, code) => Event::Alt(translate_key(code)?),
+        (KeyModifiers::SHIFT, code) => Event::Shift(translate_key(code)?),
+        (_, code) => Event::Key(translate_key(code)?),
+    })
+}
+
+fn translate_key(code: KeyCode) -> Option<Key> {
+    Some(match code {
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Delete => Key::Del,
+        KeyCode::Insert => Key::Ins,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::F(n) => Key::from_f(n),
+        _ => return None,
+    })
+}
@@ -0,0 +1,31 @@
+//! The content of an open file, shared by the global state and the editor.
+//!
+//! Every open file has one [`SharedBuffer`], the editor edits the buffer of the current file
+//! and the global state only holds a reference to it. So nothing has to be copied when the
+//! content changes, a `Rope` clone shares all of its unchanged nodes with the original.
+
+use std::sync::{Arc, Mutex};
+
+use ropey::Rope;
+
+use crate::history::History;
+
+/// A buffer shared by the global state and the editor
+pub type SharedBuffer = Arc<Mutex<Buffer>>;
+
+#[derive(Debug, Default)]
+pub struct Buffer {
+    pub content: Rope,
+    /// Undo/Redo history of the content
+    pub history: History,
+}
+
+impl Buffer {
+    /// A new shared buffer with `content` and an empty history
+    pub fn shared(content: &str) -> SharedBuffer {
+        Arc::new(Mutex::new(Self {
+            content: Rope::from_str(content),
+            history: History::default(),
+        }))
+    }
+}
@@ -0,0 +1,370 @@
+//! The user configuration, loaded from `~/.config/omega/config.toml`.
+//!
+//! Every setting is optional and falls back to its default. The file is checked for changes
+//! regularly and reloaded, an invalid configuration is reported and the previous one is kept.
+//!
+//! ```toml
+//! theme = "base16-ocean.dark"
+//! log_level = "info"
+//! keymap = "vim"
+//!
+//! [editor]
+//! tab_size = 4
+//! hard_tabs = false
+//! line_numbers = true
+//! auto_pairs = true
+//!
+//! [tree]
+//! width = 40
+//! position = "left"
+//!
+//! [languages]
+//! "*.svelte" = "HTML"
+//! "Jenkinsfile" = "Groovy"
+//!
+//! [keybindings]
+//! quick_access = "ctrl+p"
+//! undo = "ctrl+z"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, PoisonError, RwLock},
+    thread,
+    time::Duration,
+};
+
+use cursive::{
+    reexports::log::{self, LevelFilter},
+    view::SizeConstraint,
+    views::LinearLayout,
+    Cursive,
+};
+use serde::Deserialize;
+
+use crate::{
+    app::{modified, TreePanel, PKG_NAME},
+    error::{Error, Result, ResultExt},
+    indent::Indent,
+    syntax, theme,
+    ui::{
+        self, commands,
+        edit_area::EditArea,
+        keymap::{Keymap, Preset},
+    },
+};
+
+/// Interval in which the config file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The current config
+static CONFIG: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Name of the theme of the highlighting and the general styling
+    pub theme: String,
+    /// One of `off`, `error`, `warn`, `info`, `debug` or `trace`
+    pub log_level: String,
+    /// Preset of the keybindings, one of `default`, `emacs` or `vim`
+    #[serde(rename = "keymap")]
+    pub preset: Preset,
+    pub editor: EditorConfig,
+    pub tree: TreeConfig,
+    /// Languages of files by a file name pattern, where `*` matches anything
+    pub languages: BTreeMap<String, String>,
+    /// Keybindings of commands and editor actions by their id
+    pub keybindings: BTreeMap<String, String>,
+    /// The parsed `keybindings`
+    #[serde(skip)]
+    pub keymap: Keymap,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditorConfig {
+    /// Columns between the tab stops and spaces of an indentation level
+    pub tab_size: usize,
+    /// Indenting with tabs instead of spaces, unless a file is already indented otherwise
+    pub hard_tabs: bool,
+    pub line_numbers: bool,
+    /// Closing brackets and quotes automatically
+    pub auto_pairs: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TreeConfig {
+    pub width: usize,
+    pub position: TreePosition,
+}
+
+/// The side of the file tree
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TreePosition {
+    #[default]
+    Left,
+    Right,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: theme::DEFAULT_THEME.to_string(),
+            log_level: "info".to_string(),
+            preset: Preset::Default,
+            editor: EditorConfig::default(),
+            tree: TreeConfig::default(),
+            languages: BTreeMap::new(),
+            keybindings: BTreeMap::new(),
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            tab_size: 4,
+            hard_tabs: false,
+            line_numbers: true,
+            auto_pairs: true,
+        }
+    }
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self {
+            width: 40,
+            position: TreePosition::Left,
+        }
+    }
+}
+
+impl EditorConfig {
+    /// The indentation of files which aren't indented yet
+    pub fn indent(&self) -> Indent {
+        if self.hard_tabs {
+            Indent::Tabs
+        } else {
+            Indent::Spaces(self.tab_size)
+        }
+    }
+}
+
+impl Config {
+    /// Checks the values which can't be checked while parsing
+    fn validate(mut self) -> Result<Self> {
+        // custom themes are only loaded again if one is missing, e.g. after it was added
+        if !theme::themes().themes.contains_key(&self.theme)
+            && !theme::reload().themes.contains_key(&self.theme)
+        {
+            return Err(Error::Config(format!(
+                "there is no theme named \"{}\"",
+                self.theme
+            )));
+        }
+        if LevelFilter::from_str(&self.log_level).is_err() {
+            return Err(Error::Config(format!(
+                "\"{}\" is not a log level, use one of off, error, warn, info, debug or trace",
+                self.log_level
+            )));
+        }
+        if !(1..=16).contains(&self.editor.tab_size) {
+            return Err(Error::Config(
+                "the tab size has to be between 1 and 16".to_string(),
+            ));
+        }
+        if !(10..=200).contains(&self.tree.width) {
+            return Err(Error::Config(
+                "the tree width has to be between 10 and 200".to_string(),
+            ));
+        }
+        let mut syntaxes = syntax::syntaxes();
+        if self
+            .languages
+            .values()
+            .any(|name| syntax::find(&syntaxes, name).is_none())
+        {
+            syntaxes = syntax::reload();
+        }
+        if let Some(name) = self
+            .languages
+            .values()
+            .find(|name| syntax::find(&syntaxes, name).is_none())
+        {
+            return Err(Error::Config(format!(
+                "there is no language named \"{name}\""
+            )));
+        }
+        self.keymap = Keymap::new(self.preset, &self.keybindings)?;
+        Ok(self)
+    }
+}
+
+/// The current config
+pub fn get() -> Arc<Config> {
+    CONFIG
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_default()
+}
+
+/// The directory of the config file and custom themes (`~/.config/omega`)
+pub fn config_dir() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join(PKG_NAME))
+}
+
+/// The path of the config file, `None` if there is no config directory
+pub fn config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("config.toml"))
+}
+
+/// Sets the theme in the config file, keeping the rest of it as it is
+pub fn save_theme(name: &str) -> Result<()> {
+    let path =
+        config_path().ok_or_else(|| Error::Config("there is no config directory".to_string()))?;
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    let setting = format!("theme = {}", toml::Value::from(name));
+
+    // top-level settings come before the first table
+    let mut lines = content.lines().map(str::to_string).collect::<Vec<_>>();
+    let top_level = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+    let existing = lines[..top_level].iter().position(|line| {
+        line.split_once('=')
+            .is_some_and(|(key, _)| key.trim() == "theme")
+    });
+    match existing {
+        Some(i) => lines[i] = setting,
+        None => lines.insert(0, setting),
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Loads the config file, the defaults are used if it doesn't exist
+fn load() -> Result<Config> {
+    let Some(path) = config_path().filter(|path| path.exists()) else {
+        return Ok(Config::default());
+    };
+    let content = fs::read_to_string(&path)?;
+    let config: Config = toml::from_str(&content).map_err(|e| {
+        let line = e
+            .span()
+            .map_or(0, |span| content[..span.start].matches('\n').count() + 1);
+        Error::Config(format!(
+            "{}:{line}: {}",
+            path.to_string_lossy(),
+            e.message()
+        ))
+    })?;
+    config.validate()
+}
+
+/// Loads and applies the config, reloading it whenever the file changes
+pub fn start(siv: &mut Cursive) {
+    reload(siv);
+
+    let cb_sink = siv.cb_sink().clone();
+    thread::spawn(move || {
+        let mut last_modified = config_path().and_then(|path| modified(&path));
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let modified = config_path().and_then(|path| modified(&path));
+            if modified != last_modified {
+                last_modified = modified;
+                if cb_sink.send(Box::new(reload)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Loads the config file and applies it, keeping the current one if it's invalid
+fn reload(siv: &mut Cursive) {
+    match load() {
+        Ok(config) => {
+            let config = Arc::new(config);
+            let old = CONFIG
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .replace(config.clone());
+            apply(siv, old.as_deref(), &config).handle(siv);
+        }
+        Err(e) => e.to_dialog(siv),
+    }
+}
+
+/// Whether `setting` differs between `old` and `config`, always the case without an `old` one
+fn changed<T: PartialEq + ?Sized>(
+    old: Option<&Config>,
+    config: &Config,
+    setting: impl Fn(&Config) -> &T,
+) -> bool {
+    old.is_none_or(|old| setting(old) != setting(config))
+}
+
+/// Applies the settings of `config` which differ from `old`, the one applied before
+///
+/// Everything else is left as it is, e.g. a theme previewed in the picker.
+fn apply(siv: &mut Cursive, old: Option<&Config>, config: &Config) -> Result<()> {
+    if changed(old, config, |config| &config.log_level) {
+        log::set_max_level(LevelFilter::from_str(&config.log_level).unwrap_or(LevelFilter::Info));
+    }
+
+    if changed(old, config, |config| &config.keymap) {
+        let default = Keymap::default();
+        let old_keymap = old.map_or(&default, |old| &old.keymap);
+        commands::set_global_callbacks(siv, old_keymap, &config.keymap);
+        siv.call_on_name("editor", |edit_area: &mut EditArea| {
+            edit_area.set_keymap(config.keymap.editor());
+            edit_area.set_modal(config.keymap.preset == Preset::Vim);
+        });
+        ui::update_current_title(siv);
+    }
+
+    if changed(old, config, |config| &config.editor) {
+        siv.call_on_name("editor", |edit_area: &mut EditArea| {
+            edit_area.set_tab_width(config.editor.tab_size);
+            edit_area.set_line_numbers(config.editor.line_numbers);
+            edit_area.set_auto_pairs(config.editor.auto_pairs);
+        });
+        ui::update_indent(siv);
+    }
+
+    if changed(old, config, |config| &config.tree.width) {
+        siv.call_on_name("tree_title", |tree: &mut TreePanel| {
+            tree.set_width(SizeConstraint::Fixed(config.tree.width));
+        });
+    }
+    // the tree starts on the left
+    if config.tree.position != old.map_or(TreePosition::Left, |old| old.tree.position) {
+        siv.call_on_name("layout", |layout: &mut LinearLayout| {
+            layout.swap_children(0, 1);
+        });
+    }
+
+    if changed(old, config, |config| &config.languages) {
+        ui::update_language(siv);
+    }
+
+    if changed(old, config, |config| &config.theme) {
+        theme::set(siv, &config.theme)?;
+    }
+    Ok(())
+}
@@ -12,6 +12,10 @@ pub enum Error {
     FileSystem(String),
     /// The Text could not be saved to the clipboard
     Clipboard(String),
+    /// The session of a project could not be saved or restored
+    Session(String),
+    /// The config file is invalid
+    Config(String),
 }
 
 impl std::error::Error for Error {}
@@ -25,6 +29,8 @@ impl fmt::Display for Error {
                 f,
                 "Clipboard: {e}. Ensure your clipboard manager is running."
             ),
+            Error::Session(e) => write!(f, "Session: {e}."),
+            Error::Config(e) => write!(f, "Config: {e}."),
         }
     }
 }
@@ -0,0 +1,233 @@
+//! Index of all files in a project, used by the fuzzy finder of the Quick Access view.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::SystemTime,
+};
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ignore::WalkBuilder;
+
+/// Max amount of indexed files, e.g. when `/` is opened as project.
+const INDEX_LIMIT: usize = 100_000;
+
+/// Max bonus of a recently opened file, decreasing with its position in the recent files.
+const RECENT_BOOST: i64 = 64;
+
+/// A directory of the index with its modification time
+type IndexedDir = (PathBuf, Option<SystemTime>);
+
+/// The files of a project, built in the background
+#[derive(Clone, Debug, Default)]
+pub struct FileIndex {
+    files: Arc<RwLock<Arc<Vec<PathBuf>>>>,
+    /// The root of the last rebuild
+    root: Arc<RwLock<PathBuf>>,
+    /// The indexed directories with their modification time, which changes when an entry is added or removed
+    dirs: Arc<Mutex<Vec<IndexedDir>>>,
+    /// Incremented on every rebuild, so an outdated one never overwrites a newer one
+    generation: Arc<AtomicUsize>,
+}
+
+/// A file matching the query of the finder
+#[derive(Clone, Debug)]
+pub struct FileMatch {
+    pub path: PathBuf,
+    pub score: i64,
+    /// Character indices of the matched characters in the path relative to the project
+    pub indices: Vec<usize>,
+}
+
+impl FileIndex {
+    /// Rebuilds the index of `root`, calling `on_done` afterwards
+    ///
+    /// Hidden files are included, everything ignored by `.gitignore` files is skipped.
+    pub fn rebuild(&self, root: &Path, on_done: impl FnOnce() + Send + 'static) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let root = root.to_path_buf();
+        *self.root.write().unwrap() = root.clone();
+        let index = self.clone();
+
+        thread::spawn(move || {
+            let walk = WalkBuilder::new(&root)
+                .hidden(false)
+                .require_git(false)
+                .filter_entry(|entry| entry.file_name() != ".git")
+                .build();
+
+            let mut files = Vec::new();
+            let mut dirs = Vec::new();
+            for entry in walk.filter_map(|entry| entry.ok()) {
+                match entry.file_type() {
+                    Some(ty) if ty.is_dir() => {
+                        let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+                        dirs.push((entry.into_path(), modified));
+                    }
+                    Some(ty) if ty.is_file() => {
+                        files.push(entry.into_path());
+                        if files.len() == INDEX_LIMIT {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            files.sort();
+
+            if index.generation.load(Ordering::SeqCst) == generation {
+                *index.files.write().unwrap() = Arc::new(files);
+                *index.dirs.lock().unwrap() = dirs;
+                on_done();
+            }
+        });
+    }
+
+    /// Rebuilds the index if files were added to or removed from one of its directories since
+    /// the last rebuild, calling `on_done` afterwards
+    ///
+    /// Returns whether the index is rebuilt. Only the modification times of the directories are
+    /// read, so it is cheap enough to be polled.
+    pub fn update(&self, on_done: impl FnOnce() + Send + 'static) -> bool {
+        let mut changed = false;
+        for (dir, modified) in self.dirs.lock().unwrap().iter_mut() {
+            let current = fs::metadata(dir).and_then(|m| m.modified()).ok();
+            if current != *modified {
+                // a running rebuild shouldn't be started again on the next update
+                *modified = current;
+                changed = true;
+            }
+        }
+        if changed {
+            let root = self.root.read().unwrap().clone();
+            self.rebuild(&root, on_done);
+        }
+        changed
+    }
+
+    /// All indexed files
+    pub fn files(&self) -> Arc<Vec<PathBuf>> {
+        self.files.read().unwrap().clone()
+    }
+}
+
+/// Ranks `files` by how well their path relative to `root` matches `query`
+///
+/// Files in `recent_files` (most recent first) are boosted. With an empty query only the
+/// recent files are returned in their order.
+pub fn find(
+    files: &[PathBuf],
+    root: &Path,
+    query: &str,
+    recent_files: &[PathBuf],
+    limit: usize,
+) -> Vec<FileMatch> {
+    let boost = |path: &Path| {
+        recent_files
+            .iter()
+            .position(|recent| recent == path)
+            .map_or(0, |rank| RECENT_BOOST / (rank as i64 + 1))
+    };
+
+    if query.is_empty() {
+        return recent_files
+            .iter()
+            .filter(|path| path.starts_with(root))
+            .map(|path| FileMatch {
+                path: path.clone(),
+                score: boost(path),
+                indices: Vec::new(),
+            })
+            .take(limit)
+            .collect();
+    }
+
+    let matcher = SkimMatcherV2::default();
+    let mut matches = files
+        .iter()
+        .filter_map(|path| {
+            let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy();
+            let (score, indices) = matcher.fuzzy_indices(&relative, query)?;
+            Some(FileMatch {
+                path: path.clone(),
+                score: score + boost(path),
+                indices,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    matches.sort_by(|a, b| {
+        b.score.cmp(&a.score).then_with(|| {
+            let a = a.path.as_os_str().len();
+            let b = b.path.as_os_str().len();
+            a.cmp(&b)
+        })
+    });
+    matches.truncate(limit);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths
+            .iter()
+            .map(|path| Path::new("/p").join(path))
+            .collect()
+    }
+
+    fn found(matches: &[FileMatch]) -> Vec<String> {
+        matches
+            .iter()
+            .map(|m| {
+                m.path
+                    .strip_prefix("/p")
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rank_matches() {
+        let files = paths(&[
+            "src/main.rs",
+            "src/ui/mod.rs",
+            "README.md",
+            "tests/main_test.rs",
+        ]);
+        let matches = find(&files, Path::new("/p"), "main", &[], 10);
+        assert_eq!(found(&matches), ["src/main.rs", "tests/main_test.rs"]);
+        // the indices of the matched characters are relative to the project
+        assert_eq!(matches[0].indices, [4, 5, 6, 7]);
+        assert!(find(&files, Path::new("/p"), "xyz", &[], 10).is_empty());
+        assert_eq!(find(&files, Path::new("/p"), "rs", &[], 2).len(), 2);
+    }
+
+    #[test]
+    fn boost_recent_files() {
+        let files = paths(&["a/main.rs", "b/main.rs", "c/main.rs"]);
+        let recent = paths(&["c/main.rs", "b/main.rs"]);
+        let matches = find(&files, Path::new("/p"), "main", &recent, 10);
+        assert_eq!(found(&matches), ["c/main.rs", "b/main.rs", "a/main.rs"]);
+        assert!(matches[0].score > matches[1].score);
+    }
+
+    #[test]
+    fn empty_query() {
+        let files = paths(&["a.rs", "b.rs"]);
+        // only the recent files of the project, in their order
+        let mut recent = paths(&["b.rs", "a.rs"]);
+        recent.insert(1, PathBuf::from("/other/c.rs"));
+        let matches = find(&files, Path::new("/p"), "", &recent, 10);
+        assert_eq!(found(&matches), ["b.rs", "a.rs"]);
+    }
+}
@@ -0,0 +1,237 @@
+//! Searching all files of a project.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use ignore::WalkBuilder;
+
+use crate::{app::PKG_NAME, search::Search};
+
+/// Files containing a `NUL` byte within this many bytes are treated as binary.
+const BINARY_CHECK_LEN: usize = 8_000;
+
+/// Max amount of matching lines, the search stops afterwards.
+const MAX_LINES: usize = 10_000;
+
+/// A line containing at least one match
+#[derive(Clone, Debug)]
+pub struct LineMatch {
+    /// Line index, starting at 0
+    pub line: usize,
+    /// The line without its line break
+    pub text: String,
+    /// Byte ranges of the matches in `text`
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// All matching lines of a file
+#[derive(Clone, Debug)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    pub lines: Vec<LineMatch>,
+}
+
+/// The replacement of a single match in a line
+#[derive(Clone, Debug)]
+pub struct LineReplacement {
+    /// Line index, starting at 0
+    pub line: usize,
+    /// The line as it was searched, without its line break
+    pub text: String,
+    /// Byte range of the match in `text`
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+/// Searches `content` line by line
+pub fn search_content(search: &Search, content: &str) -> Vec<LineMatch> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let ranges = search.find_iter(text).collect::<Vec<_>>();
+            (!ranges.is_empty()).then(|| LineMatch {
+                line,
+                text: text.to_string(),
+                ranges,
+            })
+        })
+        .collect()
+}
+
+/// Reads the file at `path`, `None` if it's binary or not valid UTF-8
+pub fn read_text(path: &PathBuf) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    if bytes[..bytes.len().min(BINARY_CHECK_LEN)].contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Applies `replacements` to `content`, skipping those whose line changed since the search
+///
+/// Returns the new content and the amount of applied replacements.
+pub fn replace_lines(content: &str, replacements: &[LineReplacement]) -> (String, usize) {
+    let mut line_starts = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        line_starts.push(offset);
+        offset += line.len();
+    }
+
+    let mut edits = replacements
+        .iter()
+        .filter_map(|replacement| {
+            let start = *line_starts.get(replacement.line)?;
+            let line = content[start..].lines().next().unwrap_or_default();
+            (line == replacement.text).then(|| {
+                let range = start + replacement.range.start..start + replacement.range.end;
+                (range, replacement.replacement.as_str())
+            })
+        })
+        .collect::<Vec<_>>();
+    edits.sort_by_key(|(range, _)| range.start);
+    edits.dedup_by_key(|(range, _)| range.start);
+
+    let mut replaced = content.to_string();
+    for (range, replacement) in edits.iter().rev() {
+        replaced.replace_range(range.clone(), replacement);
+    }
+    (replaced, edits.len())
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it afterwards,
+/// so `path` never contains partially written content
+pub fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp_path = path.with_file_name(format!(".{name}.{PKG_NAME}.tmp"));
+    fs::write(&tmp_path, content)?;
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(&tmp_path, metadata.permissions())?;
+    }
+    let result = fs::rename(&tmp_path, path);
+    if result.is_err() {
+        fs::remove_file(&tmp_path).ok();
+    }
+    result
+}
+
+/// Searches all files in `root` in a background thread, skipping ignored and binary files
+///
+/// `buffers` contains the unsaved content of edited files, which is searched instead of the
+/// file on disk. `on_file` is called for every file with matches in path order, `on_done`
+/// at the end. The search stops early if `is_cancelled` returns true.
+pub fn spawn(
+    root: PathBuf,
+    search: Search,
+    buffers: HashMap<PathBuf, String>,
+    is_cancelled: impl Fn() -> bool + Send + 'static,
+    mut on_file: impl FnMut(FileMatches) + Send + 'static,
+    on_done: impl FnOnce() + Send + 'static,
+) {
+    thread::spawn(move || {
+        let walker = WalkBuilder::new(&root)
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+
+        let mut count = 0;
+        for entry in walker.filter_map(|entry| entry.ok()) {
+            if is_cancelled() {
+                return;
+            }
+            if !entry.file_type().is_some_and(|ty| ty.is_file()) {
+                continue;
+            }
+
+            let path = entry.into_path();
+            let content = match buffers.get(&path) {
+                Some(content) => content.clone(),
+                None => match read_text(&path) {
+                    Some(content) => content,
+                    None => continue,
+                },
+            };
+
+            let lines = search_content(&search, &content);
+            if lines.is_empty() {
+                continue;
+            }
+            count += lines.len();
+            on_file(FileMatches { path, lines });
+            if count >= MAX_LINES {
+                break;
+            }
+        }
+
+        if !is_cancelled() {
+            on_done();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchOptions;
+
+    fn replacement(line: usize, text: &str, range: Range<usize>) -> LineReplacement {
+        LineReplacement {
+            line,
+            text: text.to_string(),
+            range,
+            replacement: "new".to_string(),
+        }
+    }
+
+    #[test]
+    fn search_lines() {
+        let search = Search::new("ab", SearchOptions::default())
+            .unwrap()
+            .unwrap();
+        let lines = search_content(&search, "ab\r\nx\nab ab");
+        assert_eq!(lines.len(), 2);
+        // the line break isn't part of the text
+        assert_eq!((lines[0].line, lines[0].text.as_str()), (0, "ab"));
+        assert_eq!(lines[1].line, 2);
+        assert_eq!(lines[1].ranges, [0..2, 3..5]);
+    }
+
+    #[test]
+    fn keep_crlf() {
+        let content = "old\r\nx old\r\nold";
+        let replacements = [
+            replacement(0, "old", 0..3),
+            replacement(1, "x old", 2..5),
+            replacement(2, "old", 0..3),
+        ];
+        assert_eq!(
+            replace_lines(content, &replacements),
+            ("new\r\nx new\r\nnew".to_string(), 3)
+        );
+    }
+
+    #[test]
+    fn skip_changed_lines() {
+        let content = "old old\nchanged\n";
+        let replacements = [
+            replacement(0, "old old", 4..7),
+            replacement(0, "old old", 0..3),
+            // the same match twice is only replaced once
+            replacement(0, "old old", 0..3),
+            replacement(1, "old", 0..3),
+            replacement(5, "old", 0..3),
+        ];
+        assert_eq!(
+            replace_lines(content, &replacements),
+            ("new new\nchanged\n".to_string(), 2)
+        );
+    }
+}
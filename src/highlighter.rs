@@ -0,0 +1,190 @@
+//! Stateful syntax highlighting of a buffer.
+//!
+//! The parse state is carried from line to line, so constructs spanning multiple lines
+//! (block comments, raw strings, ...) are highlighted correctly. Highlighted lines are cached
+//! and the parser state is kept at regular checkpoints, so after an edit only the lines from
+//! the last checkpoint before the edited line onwards have to be parsed again.
+//!
+//! Besides the highlighting, the strings and comments of each line are kept, so brackets inside
+//! them can be told apart from those of the code.
+//!
+//! The parser states contain regex regions of Oniguruma, which aren't `Send`. They are kept in
+//! a thread local map instead of the highlighter, so they never leave the thread parsing with
+//! them, which is the UI thread for the editor.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ops::Range,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ropey::Rope;
+use syntect::{
+    easy::ScopeRegionIterator,
+    highlighting::{self, HighlightIterator, HighlightState, ScopeSelectors, Style, Theme},
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
+};
+
+/// Amount of lines between two cached parser states.
+const CHECKPOINT_INTERVAL: usize = 32;
+
+/// Id of the next highlighter, the key of its parser states.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The parser states of the highlighters by their id
+    static STATES: RefCell<HashMap<usize, ParserStates>> = RefCell::default();
+}
+
+/// Highlighted byte ranges of a line.
+pub type HighlightedLine = Vec<(Style, Range<usize>)>;
+
+struct ParserStates {
+    /// Parser state at the start of every `CHECKPOINT_INTERVAL`-th line
+    checkpoints: Vec<(ParseState, HighlightState)>,
+    /// Parser state at the start of the line after the last cached one
+    current: (ParseState, HighlightState),
+}
+
+#[derive(Debug)]
+pub struct Highlighter {
+    /// Key of the parser states in `STATES`
+    id: usize,
+    syntax: SyntaxReference,
+    /// Highlighted lines, always starting from the first one
+    lines: Vec<HighlightedLine>,
+    /// Byte ranges of the strings and comments of the highlighted lines
+    literals: Vec<Vec<Range<usize>>>,
+    /// Selects the scopes of strings and comments
+    literal_scopes: ScopeSelectors,
+}
+
+impl Highlighter {
+    pub fn new(syntax: &SyntaxReference) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            syntax: syntax.clone(),
+            lines: Vec::new(),
+            literals: Vec::new(),
+            literal_scopes: ScopeSelectors::from_str("string, comment").unwrap_or_default(),
+        }
+    }
+
+    /// Discards everything from `line` onwards, needs to be called after each edit.
+    pub fn invalidate(&mut self, line: usize) {
+        if line >= self.lines.len() {
+            return;
+        }
+        let checkpoint = line / CHECKPOINT_INTERVAL;
+        let kept = STATES.with_borrow_mut(|all| {
+            let states = all.get_mut(&self.id)?;
+            states.checkpoints.truncate(checkpoint + 1);
+            states.current = states.checkpoints[checkpoint].clone();
+            Some(checkpoint * CHECKPOINT_INTERVAL)
+        });
+        // without states on this thread, everything is parsed again
+        self.lines.truncate(kept.unwrap_or_default());
+        self.literals.truncate(kept.unwrap_or_default());
+    }
+
+    /// Returns the highlighting of `line`, parsing all lines before it if needed.
+    pub fn line(
+        &mut self,
+        content: &Rope,
+        line: usize,
+        syntax_set: &SyntaxSet,
+        theme: &Theme,
+    ) -> &[(Style, Range<usize>)] {
+        self.parse(content, line, syntax_set, theme);
+        self.lines.get(line).map_or(&[], |line| line.as_slice())
+    }
+
+    /// Returns the byte ranges of the strings and comments of `line`, parsing all lines before
+    /// it if needed.
+    pub fn literals(
+        &mut self,
+        content: &Rope,
+        line: usize,
+        syntax_set: &SyntaxSet,
+        theme: &Theme,
+    ) -> &[Range<usize>] {
+        self.parse(content, line, syntax_set, theme);
+        self.literals.get(line).map_or(&[], |line| line.as_slice())
+    }
+
+    /// Parses and highlights the lines up to `line`.
+    fn parse(&mut self, content: &Rope, line: usize, syntax_set: &SyntaxSet, theme: &Theme) {
+        let highlighter = highlighting::Highlighter::new(theme);
+
+        STATES.with_borrow_mut(|all| {
+            let states = all.entry(self.id).or_insert_with(|| {
+                self.lines.clear();
+                self.literals.clear();
+                let initial = (
+                    ParseState::new(&self.syntax),
+                    HighlightState::new(&highlighter, ScopeStack::new()),
+                );
+                ParserStates {
+                    checkpoints: Vec::new(),
+                    current: initial,
+                }
+            });
+
+            while self.lines.len() <= line && self.lines.len() < content.len_lines() {
+                let index = self.lines.len();
+                if index % CHECKPOINT_INTERVAL == 0
+                    && states.checkpoints.len() == index / CHECKPOINT_INTERVAL
+                {
+                    states.checkpoints.push(states.current.clone());
+                }
+
+                let text = content.line(index).to_string();
+                let (parse_state, highlight_state) = &mut states.current;
+                let ops = parse_state
+                    .parse_line(&text, syntax_set)
+                    .unwrap_or_default();
+
+                // The scopes at the start of the line, before the highlighting moves on.
+                let mut scopes = highlight_state.path.clone();
+                let mut literals: Vec<Range<usize>> = Vec::new();
+                let mut start = 0;
+                for (region, op) in ScopeRegionIterator::new(&ops, &text) {
+                    scopes.apply(op).ok();
+                    let range = start..start + region.len();
+                    start = range.end;
+                    if range.is_empty()
+                        || self.literal_scopes.does_match(scopes.as_slice()).is_none()
+                    {
+                        continue;
+                    }
+                    match literals.last_mut() {
+                        Some(last) if last.end == range.start => last.end = range.end,
+                        _ => literals.push(range),
+                    }
+                }
+
+                let mut start = 0;
+                let highlighted =
+                    HighlightIterator::new(highlight_state, &ops, &text, &highlighter)
+                        .map(|(style, part)| {
+                            let range = start..start + part.len();
+                            start = range.end;
+                            (style, range)
+                        })
+                        .collect();
+                self.lines.push(highlighted);
+                self.literals.push(literals);
+            }
+        });
+    }
+}
+
+impl Drop for Highlighter {
+    fn drop(&mut self) {
+        STATES
+            .try_with(|all| all.borrow_mut().remove(&self.id))
+            .ok();
+    }
+}
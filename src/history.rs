@@ -0,0 +1,157 @@
+//! Undo/Redo history of a buffer.
+//!
+//! Every entry is a full snapshot of the content. This is cheap because a `Rope` clone
+//! shares all of its unchanged nodes with the original.
+
+use cursive::Vec2;
+use ropey::Rope;
+
+use crate::ui::edit_area::Cursor;
+
+/// Max amount of undo steps kept per buffer.
+const HISTORY_LIMIT: usize = 1_000;
+
+/// The kind of a modification, used for grouping edits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditKind {
+    /// Typing characters, merged with directly following typed characters.
+    Insert,
+    /// Removing characters via `Backspace`/`Del`, merged like `Insert`.
+    Delete,
+    /// Any other edit (paste, cut, indent, line moves, ...), never merged.
+    Other,
+}
+
+/// The state of a buffer at a specific point in time.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    pub content: Rope,
+    pub cursor: Cursor,
+    pub scroll_offset: Vec2,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// Kind of the last recorded edit, `None` if the current group was sealed.
+    last_kind: Option<EditKind>,
+}
+
+impl History {
+    /// Records `snapshot`, the state before an edit of `kind` happens.
+    ///
+    /// If the edit continues the current group (e.g. typing another character) nothing is
+    /// recorded, so undoing reverts the whole group at once.
+    pub fn record(&mut self, kind: EditKind, snapshot: Snapshot) {
+        let merge = kind != EditKind::Other && self.last_kind == Some(kind);
+        self.last_kind = Some(kind);
+        self.redo.clear();
+
+        if merge {
+            return;
+        }
+
+        self.undo.push(snapshot);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Ends the current group, the next edit will always create a new undo step.
+    pub fn seal(&mut self) {
+        self.last_kind = None;
+    }
+
+    /// Returns the state to restore, `current` is kept for redoing.
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.undo.pop()?;
+        self.redo.push(current);
+        self.seal();
+        Some(snapshot)
+    }
+
+    /// Returns the state to restore, `current` is kept for undoing.
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.redo.pop()?;
+        self.undo.push(current);
+        self.seal();
+        Some(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(content: &str) -> Snapshot {
+        Snapshot {
+            content: Rope::from_str(content),
+            ..Default::default()
+        }
+    }
+
+    fn undo(history: &mut History, current: &str) -> Option<String> {
+        history
+            .undo(snapshot(current))
+            .map(|snapshot| snapshot.content.to_string())
+    }
+
+    #[test]
+    fn merge_typing() {
+        let mut history = History::default();
+        history.record(EditKind::Insert, snapshot(""));
+        history.record(EditKind::Insert, snapshot("a"));
+        history.record(EditKind::Insert, snapshot("ab"));
+        // switching from typing to deleting starts a new group
+        history.record(EditKind::Delete, snapshot("abc"));
+        history.record(EditKind::Delete, snapshot("ab"));
+
+        assert_eq!(undo(&mut history, "a").as_deref(), Some("abc"));
+        assert_eq!(undo(&mut history, "abc").as_deref(), Some(""));
+        assert_eq!(undo(&mut history, ""), None);
+    }
+
+    #[test]
+    fn seal_and_other_edits() {
+        let mut history = History::default();
+        history.record(EditKind::Insert, snapshot(""));
+        history.seal();
+        history.record(EditKind::Insert, snapshot("a"));
+        history.record(EditKind::Other, snapshot("ab"));
+        history.record(EditKind::Other, snapshot("abc"));
+
+        assert_eq!(undo(&mut history, "abcd").as_deref(), Some("abc"));
+        assert_eq!(undo(&mut history, "abc").as_deref(), Some("ab"));
+        assert_eq!(undo(&mut history, "ab").as_deref(), Some("a"));
+        assert_eq!(undo(&mut history, "a").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn redo_after_undo() {
+        let mut history = History::default();
+        history.record(EditKind::Insert, snapshot(""));
+        assert_eq!(undo(&mut history, "a").as_deref(), Some(""));
+        let redone = history.redo(snapshot("")).unwrap();
+        assert_eq!(redone.content, "a");
+
+        // typing after an undo continues a new group and discards the redo steps
+        assert_eq!(undo(&mut history, "a").as_deref(), Some(""));
+        history.record(EditKind::Insert, snapshot(""));
+        assert!(history.redo(snapshot("b")).is_none());
+        assert_eq!(undo(&mut history, "b").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn limit() {
+        let mut history = History::default();
+        for i in 0..=HISTORY_LIMIT {
+            history.record(EditKind::Other, snapshot(&i.to_string()));
+        }
+        let mut oldest = None;
+        while let Some(content) = undo(&mut history, "") {
+            oldest = Some(content);
+        }
+        assert_eq!(oldest.as_deref(), Some("1"));
+    }
+}
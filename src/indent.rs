@@ -0,0 +1,162 @@
+//! Indentation styles of buffers, detected from their content.
+
+use std::fmt;
+
+use ropey::RopeSlice;
+
+/// Lines looked at to detect the indentation, enough for a reliable guess
+const DETECT_LINES: usize = 1000;
+
+/// How lines are indented
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    /// One tab per level
+    Tabs,
+    /// The amount of spaces per level
+    Spaces(usize),
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(4)
+    }
+}
+
+impl fmt::Display for Indent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Indent::Tabs => write!(f, "tabs"),
+            Indent::Spaces(n) => write!(f, "{n} spaces"),
+        }
+    }
+}
+
+impl Indent {
+    /// The text inserted by indenting once
+    pub fn unit(self) -> String {
+        match self {
+            Indent::Tabs => "\t".to_string(),
+            Indent::Spaces(n) => " ".repeat(n),
+        }
+    }
+
+    /// Columns of one level, tabs are `tab_width` wide
+    pub fn width(self, tab_width: usize) -> usize {
+        match self {
+            Indent::Tabs => tab_width,
+            Indent::Spaces(n) => n,
+        }
+    }
+
+    /// Detects the indentation of `content`, `None` if there are no indented lines.
+    ///
+    /// The width of spaces is the most common step between the indentation of neighbouring lines.
+    pub fn detect(content: RopeSlice) -> Option<Self> {
+        let mut tabs = 0;
+        let mut spaces = 0;
+        // how often the indentation changed by 2 to 8 spaces
+        let mut steps = [0usize; 9];
+        let mut previous = 0;
+        for line in content.lines().take(DETECT_LINES) {
+            if line.chars().all(char::is_whitespace) {
+                continue;
+            }
+            if line.char(0) == '\t' {
+                tabs += 1;
+                continue;
+            }
+            let indentation = line.chars().take_while(|&c| c == ' ').count();
+            if indentation > 0 {
+                spaces += 1;
+            }
+            let step = indentation.abs_diff(previous);
+            if (2..steps.len()).contains(&step) {
+                steps[step] += 1;
+            }
+            previous = indentation;
+        }
+
+        if tabs == 0 && spaces == 0 {
+            None
+        } else if tabs > spaces {
+            Some(Indent::Tabs)
+        } else {
+            // the smaller width wins a tie
+            let (width, count) = steps
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, &count)| count)?;
+            (*count > 0).then_some(Indent::Spaces(width))
+        }
+    }
+
+    /// `content` with the indentation of every line converted from this style to `to`.
+    ///
+    /// Leading whitespace which isn't a full level is kept as spaces.
+    pub fn convert(self, content: RopeSlice, to: Indent, tab_width: usize) -> String {
+        let from_width = self.width(tab_width);
+        let mut converted = String::with_capacity(content.len_bytes());
+        for line in content.lines() {
+            let line = line.to_string();
+            let rest = line.trim_start_matches([' ', '\t']);
+            let columns = line[..line.len() - rest.len()]
+                .chars()
+                .fold(0, |col, c| match c {
+                    '\t' => col + tab_width - col % tab_width,
+                    _ => col + 1,
+                });
+            converted.push_str(&to.unit().repeat(columns / from_width));
+            converted.push_str(&" ".repeat(columns % from_width));
+            converted.push_str(rest);
+        }
+        converted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+
+    fn detect(content: &str) -> Option<Indent> {
+        Indent::detect(Rope::from_str(content).slice(..))
+    }
+
+    #[test]
+    fn detect_tabs_and_spaces() {
+        assert_eq!(detect("fn a() {\n\tb();\n\tc();\n}\n"), Some(Indent::Tabs));
+        assert_eq!(detect("a:\n  b:\n    c\n  d\n"), Some(Indent::Spaces(2)));
+        assert_eq!(
+            detect("a {\n    b {\n        c\n    }\n}\n"),
+            Some(Indent::Spaces(4))
+        );
+        assert_eq!(detect("a\nb\n\n   \n"), None);
+    }
+
+    #[test]
+    fn detect_mixed() {
+        // the majority of the indented lines wins
+        assert_eq!(detect("a\n\tb\n\tc\n    d\n"), Some(Indent::Tabs));
+        assert_eq!(detect("a\n    b\n    c\n\td\n"), Some(Indent::Spaces(4)));
+        // a tie of steps is won by the smaller width
+        assert_eq!(detect("a\n  b\nc\n    d\n"), Some(Indent::Spaces(2)));
+        // a continuation aligned with a single space isn't a level
+        assert_eq!(detect("/**\n * a\n */\n"), None);
+    }
+
+    #[test]
+    fn convert() {
+        let content = Rope::from_str("a\n    b\n        c\n      d\n");
+        assert_eq!(
+            Indent::Spaces(4).convert(content.slice(..), Indent::Tabs, 4),
+            "a\n\tb\n\t\tc\n\t  d\n"
+        );
+        let content = Rope::from_str("a\n\tb\n\t\tc\n  \td\n");
+        assert_eq!(
+            Indent::Tabs.convert(content.slice(..), Indent::Spaces(2), 4),
+            "a\n  b\n    c\n  d\n"
+        );
+    }
+}
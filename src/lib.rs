@@ -0,0 +1,26 @@
+//! The editor as a library, used by the binary and the benchmarks.
+
+#![warn(clippy::semicolon_if_nothing_returned)]
+#![warn(clippy::manual_string_new)]
+#![warn(clippy::map_unwrap_or)]
+#![warn(clippy::implicit_clone)]
+
+pub mod app;
+pub mod backend;
+pub mod buffer;
+pub mod clipboard;
+pub mod config;
+pub mod error;
+pub mod file_index;
+pub mod grep;
+pub mod highlighter;
+pub mod history;
+pub mod indent;
+pub mod search;
+pub mod session;
+pub mod swap;
+pub mod syntax;
+pub mod theme;
+pub mod ui;
+pub mod watcher;
+pub mod wrap;
@@ -3,14 +3,10 @@
 #![warn(clippy::map_unwrap_or)]
 #![warn(clippy::implicit_clone)]
 
-pub mod app;
-pub mod clipboard;
-pub mod error;
-pub mod ui;
-
 use cursive::logger::reserve_logs;
 use cursive::logger::CursiveLogger;
 use cursive::reexports::log;
+use omega::app;
 
 fn main() {
     logging();
@@ -0,0 +1,364 @@
+//! Searching inside a buffer.
+
+use std::{
+    cmp::{max, min},
+    ops::Range,
+};
+
+use regex::{Captures, Regex, RegexBuilder};
+use ropey::Rope;
+
+use crate::error::{Error, Result};
+
+/// Options of a search, toggled in the search bar.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Interpret the query as regular expression, allowing `$1`-style captures in replacements
+    pub regex: bool,
+}
+
+/// The bytes `start..old_end` of the content that became `start..new_end`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edit {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl Edit {
+    /// Combines this edit with the following `edit` of its result into one covering both.
+    pub fn then(self, edit: Edit) -> Edit {
+        let end = max(self.new_end, edit.old_end);
+        Edit {
+            start: min(self.start, edit.start),
+            old_end: self.old_end + (end - self.new_end),
+            new_end: end - edit.old_end + edit.new_end,
+        }
+    }
+}
+
+/// A search query with all of its matches in the current content.
+#[derive(Clone, Debug)]
+pub struct Search {
+    regex: Regex,
+    /// Expand captures in replacements
+    expand: bool,
+    /// Byte ranges of all matches, sorted and non-overlapping
+    matches: Vec<Range<usize>>,
+}
+
+impl Search {
+    /// Creates a search for `query`, `None` if the query is empty.
+    pub fn new(query: &str, options: SearchOptions) -> Result<Option<Self>> {
+        if query.is_empty() {
+            return Ok(None);
+        }
+
+        let mut pattern = if options.regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{pattern})\b");
+        }
+
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .build()
+            .map_err(|e| Error::Arguments(e.to_string()))?;
+
+        Ok(Some(Self {
+            regex,
+            expand: options.regex,
+            matches: Vec::new(),
+        }))
+    }
+
+    /// Finds all matches in `content`, needs to be called after each edit.
+    pub fn find(&mut self, content: &Rope) {
+        let text = content.to_string();
+        self.matches = self.find_iter(&text).collect();
+    }
+
+    /// Updates the matches after `edit`, only the lines around it are searched again.
+    ///
+    /// Matches behind the edited lines are shifted. Ones reaching into the edited lines from
+    /// outside, only possible with patterns matching line breaks, are dropped.
+    pub fn update(&mut self, content: &Rope, edit: Edit) {
+        let first = content.byte_to_line(edit.start);
+        let last = content.byte_to_line(edit.new_end);
+        let start = content.line_to_byte(first);
+        let end = content.line_to_byte(min(last + 1, content.len_lines()));
+        let old_end = end - edit.new_end + edit.old_end;
+
+        // the lines before and after keep anchors and word boundaries correct
+        let context_start = content.line_to_byte(first.saturating_sub(1));
+        let context_end = content.line_to_byte(min(last + 2, content.len_lines()));
+        let text = content.byte_slice(context_start..context_end).to_string();
+        let found = self
+            .find_between(&text, start - context_start, end - context_start)
+            .map(|m| m.start + context_start..m.end + context_start)
+            .collect::<Vec<_>>();
+
+        let before = self.matches.partition_point(|m| m.end <= start);
+        let after = self.matches.partition_point(|m| m.start < old_end);
+        let shifted = self.matches[after.max(before)..]
+            .iter()
+            .map(|m| m.start - old_end + end..m.end - old_end + end)
+            .collect::<Vec<_>>();
+        self.matches.truncate(before);
+        self.matches.extend(found);
+        self.matches.extend(shifted);
+    }
+
+    /// Iterates over the non-empty matches in `text` between the bytes `start` and `end`,
+    /// the text around them is only used as context.
+    fn find_between<'a>(
+        &'a self,
+        text: &'a str,
+        start: usize,
+        end: usize,
+    ) -> impl Iterator<Item = Range<usize>> + 'a {
+        let mut offset = start;
+        std::iter::from_fn(move || loop {
+            let found = self.regex.find_at(text, offset)?;
+            if found.start() >= end {
+                return None;
+            }
+            if found.is_empty() {
+                // continue behind the next character
+                let next = text[found.end()..].chars().next()?;
+                offset = found.end() + next.len_utf8();
+                continue;
+            }
+            offset = found.end();
+            if found.end() <= end {
+                return Some(found.range());
+            }
+        })
+    }
+
+    /// Iterates over the byte ranges of all non-empty matches in `text`.
+    pub fn find_iter<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.regex
+            .find_iter(text)
+            .filter(|m| !m.is_empty())
+            .map(|m| m.range())
+    }
+
+    /// Computes the replacements of all matches, in the same order as `matches`.
+    ///
+    /// In regex mode captures like `$1` or `${name}` in `replacement` are expanded.
+    pub fn replacements(&self, content: &Rope, replacement: &str) -> Vec<(Range<usize>, String)> {
+        self.replacements_in(&content.to_string(), replacement)
+    }
+
+    /// Computes the replacements of all matches in `text`, like `replacements`.
+    pub fn replacements_in(&self, text: &str, replacement: &str) -> Vec<(Range<usize>, String)> {
+        self.regex
+            .captures_iter(text)
+            .filter_map(|captures| {
+                let range = captures.get(0)?.range();
+                if range.is_empty() {
+                    return None;
+                }
+                Some((range, self.expand(&captures, replacement)))
+            })
+            .collect()
+    }
+
+    /// Computes the replacement of the match starting at the byte `start` of `text`.
+    pub fn replacement_at(&self, text: &str, start: usize, replacement: &str) -> Option<String> {
+        let captures = self.regex.captures_at(text, start)?;
+        let found = captures.get(0)?;
+        (found.start() == start && !found.is_empty()).then(|| self.expand(&captures, replacement))
+    }
+
+    fn expand(&self, captures: &Captures, replacement: &str) -> String {
+        let mut replaced = String::new();
+        if self.expand {
+            captures.expand(replacement, &mut replaced);
+        } else {
+            replaced.push_str(replacement);
+        }
+        replaced
+    }
+
+    /// All matches in the content.
+    pub fn matches(&self) -> &[Range<usize>] {
+        &self.matches
+    }
+
+    /// Returns the matches overlapping with the byte `range`.
+    pub fn matches_in(&self, range: Range<usize>) -> &[Range<usize>] {
+        let start = self.matches.partition_point(|m| m.end <= range.start);
+        let end = self.matches.partition_point(|m| m.start < range.end);
+        &self.matches[start..end.max(start)]
+    }
+
+    /// Index of the next match starting at or after `byte_offset`, wrapping around at the end.
+    pub fn next(&self, byte_offset: usize) -> Option<usize> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let index = self.matches.partition_point(|m| m.start < byte_offset);
+        Some(index % self.matches.len())
+    }
+
+    /// Index of the last match starting before `byte_offset`, wrapping around at the start.
+    pub fn previous(&self, byte_offset: usize) -> Option<usize> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let index = self.matches.partition_point(|m| m.start < byte_offset);
+        Some(index.checked_sub(1).unwrap_or(self.matches.len() - 1))
+    }
+
+    /// Index of the match starting at exactly `byte_offset`.
+    pub fn position(&self, byte_offset: usize) -> Option<usize> {
+        self.matches
+            .binary_search_by_key(&byte_offset, |m| m.start)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(query: &str, options: SearchOptions) -> Search {
+        Search::new(query, options).unwrap().unwrap()
+    }
+
+    #[test]
+    fn expand_captures() {
+        let options = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        let content = Rope::from_str("let a = 1;\nlet bc = 23;");
+        let search = search(r"let (\w+) = (?<value>\d+)", options);
+        assert_eq!(
+            search.replacements(&content, "const $1: i32 = ${value}"),
+            [
+                (0..9, "const a: i32 = 1".to_string()),
+                (11..22, "const bc: i32 = 23".to_string())
+            ]
+        );
+        assert_eq!(
+            search.replacement_at("x let d = 4", 2, "$1$1").as_deref(),
+            Some("dd")
+        );
+        // only matches starting exactly there
+        assert_eq!(search.replacement_at("x let d = 4", 1, "$1"), None);
+    }
+
+    #[test]
+    fn plain_replacement() {
+        // without regex mode the query and the replacement are taken literally
+        let search = search("a.b", SearchOptions::default());
+        assert_eq!(
+            search.replacements_in("a.b axb A.B", "$1"),
+            [(0..3, "$1".to_string()), (8..11, "$1".to_string())]
+        );
+    }
+
+    #[test]
+    fn options() {
+        let text = "Word word words sword";
+        let case_sensitive = search(
+            "word",
+            SearchOptions {
+                case_sensitive: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(case_sensitive.find_iter(text).count(), 3);
+        let whole_word = search(
+            "word",
+            SearchOptions {
+                whole_word: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(whole_word.find_iter(text).collect::<Vec<_>>(), [0..4, 5..9]);
+
+        assert!(Search::new("", SearchOptions::default()).unwrap().is_none());
+        let invalid = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert!(Search::new("(", invalid).is_err());
+    }
+
+    #[test]
+    fn next_and_previous() {
+        let mut search = search("ab", SearchOptions::default());
+        search.find(&Rope::from_str("ab ab ab"));
+        assert_eq!(search.next(0), Some(0));
+        assert_eq!(search.next(1), Some(1));
+        // wrapping around
+        assert_eq!(search.next(7), Some(0));
+        assert_eq!(search.previous(0), Some(2));
+        assert_eq!(search.previous(3), Some(0));
+        assert_eq!(search.previous(4), Some(1));
+        assert_eq!(search.position(6), Some(2));
+        assert_eq!(search.position(5), None);
+        assert_eq!(search.matches_in(1..4), [0..2, 3..5]);
+    }
+
+    #[test]
+    fn update_edited_lines() {
+        let mut content = Rope::from_str("ab\nxab\nab ab");
+        let mut search = search("ab", SearchOptions::default());
+        search.find(&content);
+        assert_eq!(search.matches(), [0..2, 4..6, 7..9, 10..12]);
+
+        // typing `a` in front of `b` on the second line, the later matches are shifted
+        content.insert(4, "a");
+        let edit = Edit {
+            start: 4,
+            old_end: 4,
+            new_end: 5,
+        };
+        search.update(&content, edit);
+        assert_eq!(search.matches(), [0..2, 5..7, 8..10, 11..13]);
+
+        // removing the line break merges the lines
+        content.remove(7..8);
+        search.update(
+            &content,
+            Edit {
+                start: 7,
+                old_end: 8,
+                new_end: 7,
+            },
+        );
+        assert_eq!(search.matches(), [0..2, 5..7, 7..9, 10..12]);
+    }
+
+    #[test]
+    fn merge_edits() {
+        let insert = Edit {
+            start: 10,
+            old_end: 10,
+            new_end: 15,
+        };
+        let remove = Edit {
+            start: 2,
+            old_end: 12,
+            new_end: 2,
+        };
+        assert_eq!(
+            insert.then(remove),
+            Edit {
+                start: 2,
+                old_end: 10,
+                new_end: 5,
+            }
+        );
+    }
+}
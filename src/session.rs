@@ -0,0 +1,142 @@
+//! Persisting the open files of a project between runs.
+//!
+//! A session is stored as TOML in the XDG state directory (`~/.local/state/omega/sessions/`),
+//! one file per project, named after the project path.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use cursive::{Cursive, Vec2};
+use cursive_tree_view::TreeView;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{state_dir, state_file_name, State},
+    config,
+    error::{Error, Result},
+    ui::{
+        edit_area::Cursor,
+        file_tree::{self, TreeEntry},
+        open_file,
+    },
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    /// The file shown in the editor
+    pub current_file: Option<PathBuf>,
+    #[serde(default)]
+    pub files: Vec<FileSession>,
+    /// Expanded directories of the file tree
+    #[serde(default)]
+    pub expanded_dirs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileSession {
+    pub path: PathBuf,
+    /// Character offset of the cursor
+    pub cursor: usize,
+    pub scroll_offset: (usize, usize),
+}
+
+/// The session file of `project_path`, `None` if there is no state directory
+fn session_path(project_path: &Path) -> Option<PathBuf> {
+    let name = state_file_name(project_path) + ".toml";
+    Some(state_dir()?.join("sessions").join(name))
+}
+
+/// Saves the open files and the expanded directories of the current project
+pub fn save(siv: &mut Cursive) -> Result<()> {
+    let Some((project_path, current_file, mut files)) = siv.with_user_data(|state: &mut State| {
+        let files = state
+            .files
+            .iter()
+            .filter(|(path, _)| path.starts_with(&state.project_path))
+            .map(|(path, data)| FileSession {
+                path: path.clone(),
+                cursor: data.cursor.char_offset,
+                scroll_offset: (data.scroll_offset.x, data.scroll_offset.y),
+            })
+            .collect::<Vec<_>>();
+        (
+            state.project_path.clone(),
+            state.current_file.clone(),
+            files,
+        )
+    }) else {
+        return Ok(());
+    };
+    let Some(path) = session_path(&project_path) else {
+        return Ok(());
+    };
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let expanded_dirs = siv
+        .call_on_name("tree", |tree: &mut TreeView<TreeEntry>| {
+            file_tree::expanded_dirs(tree)
+        })
+        .unwrap_or_default();
+
+    let session = Session {
+        current_file: current_file.filter(|path| path.starts_with(&project_path)),
+        files,
+        expanded_dirs,
+    };
+
+    let content = toml::to_string(&session).map_err(|e| Error::Session(e.to_string()))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Restores the session of the current project, if there is one
+///
+/// A file which is already open (e.g. passed as argument) stays the active one.
+pub fn restore(siv: &mut Cursive) -> Result<()> {
+    let Some((project_path, opened_file)) = siv.with_user_data(|state: &mut State| {
+        (state.project_path.clone(), state.current_file.clone())
+    }) else {
+        return Ok(());
+    };
+    let Some(path) = session_path(&project_path) else {
+        return Ok(());
+    };
+    if !path.is_file() {
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let session: Session = toml::from_str(&content).map_err(|e| Error::Session(e.to_string()))?;
+
+    siv.call_on_name("tree", |tree: &mut TreeView<TreeEntry>| {
+        file_tree::expand_dirs(tree, &session.expanded_dirs);
+    });
+
+    // Files that were deleted in the meantime are skipped.
+    for file in session.files.iter().filter(|file| file.path.is_file()) {
+        if let Err(e) = open_file(siv, &file.path) {
+            e.to_dialog(siv);
+            continue;
+        }
+        siv.with_user_data(|state: &mut State| {
+            if let Some(data) = state.files.get_mut(&file.path) {
+                let tab_width = config::get().editor.tab_size;
+                data.cursor = Cursor::from_char_offset(&data.content(), file.cursor, tab_width);
+                data.scroll_offset = Vec2::from(file.scroll_offset);
+            }
+        });
+    }
+
+    let current_file = opened_file
+        .or(session.current_file)
+        .filter(|path| path.is_file());
+    if let Some(current_file) = current_file {
+        open_file(siv, &current_file)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! Crash recovery of unsaved buffers.
+//!
+//! The content of every edited buffer is written to a swap file in the state directory
+//! (`~/.local/state/omega/swap/`) by a background thread and once more from the panic hook.
+//! A swap file is removed as soon as its buffer is saved or its changes are discarded.
+
+use std::{
+    collections::BTreeMap,
+    fs, io, panic,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::Show,
+    event::DisableMouseCapture,
+    execute,
+    terminal::{self, LeaveAlternateScreen},
+};
+use cursive::{
+    reexports::log::error,
+    view::Resizable,
+    views::{Dialog, LinearLayout, TextView},
+    Cursive,
+};
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{state_dir, state_file_name, State},
+    error::{Error, Result, ResultExt},
+    ui::{diff, edit_area::EditArea, open_file},
+};
+
+/// Interval in which changed buffers are written to their swap files.
+const SWAP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Name of the background thread writing the swap files.
+const SWAP_THREAD: &str = "swap";
+
+/// Changes not yet written, `None` if the swap file should be removed
+static PENDING: Mutex<BTreeMap<PathBuf, Option<Rope>>> = Mutex::new(BTreeMap::new());
+
+/// The content of a swap file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Swap {
+    /// The file the buffer belongs to
+    pub path: PathBuf,
+    pub content: String,
+}
+
+fn pending() -> MutexGuard<'static, BTreeMap<PathBuf, Option<Rope>>> {
+    PENDING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The directory containing all swap files, `None` if there is no state directory
+fn swap_dir() -> Option<PathBuf> {
+    Some(state_dir()?.join("swap"))
+}
+
+/// The swap file of the buffer at `path`
+fn swap_path(path: &Path) -> Option<PathBuf> {
+    Some(swap_dir()?.join(state_file_name(path) + ".swp"))
+}
+
+/// Marks the buffer at `path` as changed, its swap file will be updated in the background
+///
+/// The content is only converted to a string when it's written, as it's updated on every edit.
+pub fn update(path: &Path, content: Rope) {
+    pending().insert(path.to_path_buf(), Some(content));
+}
+
+/// Marks the buffer at `path` as unchanged, its swap file will be removed in the background
+pub fn remove(path: &Path) {
+    pending().insert(path.to_path_buf(), None);
+}
+
+/// Writes all pending changes to the swap files
+pub fn flush() {
+    let mut pending = pending();
+    for (path, content) in std::mem::take(&mut *pending) {
+        let Some(swap_path) = swap_path(&path) else {
+            continue;
+        };
+        let result = match content {
+            Some(content) => write(
+                &swap_path,
+                &Swap {
+                    path,
+                    content: content.to_string(),
+                },
+            ),
+            None => match fs::remove_file(&swap_path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+        };
+        if let Err(e) = result {
+            error!("Failed to update swap file {swap_path:?}: {e}");
+        }
+    }
+}
+
+/// Writes the swap file atomically, so a crash while writing never corrupts it
+fn write(swap_path: &Path, swap: &Swap) -> Result<()> {
+    let content = toml::to_string(swap).map_err(|e| Error::FileSystem(e.to_string()))?;
+    if let Some(parent) = swap_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = swap_path.with_extension("swp.tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(tmp_path, swap_path)?;
+    Ok(())
+}
+
+/// Starts writing the swap files in the background and installs the panic hook
+///
+/// On a panic the pending changes are written once more and the terminal is restored before
+/// the panic message is printed.
+pub fn start() {
+    thread::Builder::new()
+        .name(SWAP_THREAD.to_string())
+        .spawn(|| loop {
+            thread::sleep(SWAP_INTERVAL);
+            flush();
+        })
+        .expect("failed to spawn swap thread");
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // The swap thread could be holding the lock while panicking.
+        if thread::current().name() != Some(SWAP_THREAD) {
+            flush();
+        }
+        terminal::disable_raw_mode().ok();
+        execute!(
+            io::stdout(),
+            DisableMouseCapture,
+            LeaveAlternateScreen,
+            Show
+        )
+        .ok();
+        default_hook(info);
+    }));
+}
+
+/// Offers to recover or discard every swap file of a buffer in the current project
+pub fn recover(siv: &mut Cursive) -> Result<()> {
+    let Some(dir) = swap_dir() else {
+        return Ok(());
+    };
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let project_path = siv
+        .with_user_data(|state: &mut State| state.project_path.clone())
+        .unwrap_or_default();
+
+    for entry in fs::read_dir(dir)? {
+        let swap_path = entry?.path();
+        if swap_path.extension().unwrap_or_default() != "swp" {
+            continue;
+        }
+        let swap: Swap = match toml::from_str(&fs::read_to_string(&swap_path)?) {
+            Ok(swap) => swap,
+            Err(e) => {
+                error!("Invalid swap file {swap_path:?}: {e}");
+                continue;
+            }
+        };
+        if !swap.path.starts_with(&project_path) {
+            continue;
+        }
+
+        let on_disk = fs::read_to_string(&swap.path).unwrap_or_default();
+        if on_disk == swap.content {
+            fs::remove_file(&swap_path)?;
+            continue;
+        }
+
+        let layout = LinearLayout::vertical()
+            .child(TextView::new(format!(
+                "\"{}\" has unsaved changes from a previous session. Recover them?",
+                swap.path.to_string_lossy()
+            )))
+            .child(TextView::new(" "))
+            .child(diff::new(&on_disk, &swap.content));
+
+        let path = swap.path.clone();
+        siv.add_layer(
+            Dialog::new()
+                .title("Recover")
+                .padding_lrtb(1, 1, 1, 0)
+                .content(layout)
+                .button("Recover", move |siv| {
+                    siv.pop_layer();
+                    restore(siv, &swap).handle(siv);
+                })
+                .button("Discard", move |siv| {
+                    siv.pop_layer();
+                    remove(&path);
+                })
+                .full_width(),
+        );
+    }
+    Ok(())
+}
+
+/// Opens the file of `swap` with the recovered content as unsaved changes
+///
+/// A file which was deleted in the meantime is only created again by saving it.
+fn restore(siv: &mut Cursive, swap: &Swap) -> Result<()> {
+    if !swap.path.exists() {
+        siv.with_user_data(|state: &mut State| {
+            state.files.entry(swap.path.clone()).or_default();
+        });
+    }
+    open_file(siv, &swap.path)?;
+    if let Some(cb) = siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        edit_area.set_content(swap.content.clone())
+    }) {
+        cb(siv);
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Syntax definitions of the highlighting and detecting the language of a file.
+//!
+//! Besides the built-in syntaxes, every `.sublime-syntax` file in the `syntaxes` directory of
+//! the config directory (`~/.config/omega/syntaxes/`) is loaded.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use cursive::reexports::log::error;
+use syntect::parsing::{SyntaxDefinition, SyntaxReference, SyntaxSet};
+
+use crate::config;
+
+/// Chooses the detected language again via `>language auto`.
+pub const AUTO: &str = "auto";
+
+/// Close enough languages of file names and extensions the built-in syntaxes don't know,
+/// used unless a custom syntax covers them
+const FALLBACKS: &[(&str, &str)] = &[
+    (".zshrc", "Bourne Again Shell (bash)"),
+    (".zprofile", "Bourne Again Shell (bash)"),
+    (".zshenv", "Bourne Again Shell (bash)"),
+    (".env", "Bourne Again Shell (bash)"),
+    ("PKGBUILD", "Bourne Again Shell (bash)"),
+    ("Dockerfile", "Bourne Again Shell (bash)"),
+    ("Containerfile", "Bourne Again Shell (bash)"),
+    ("justfile", "Makefile"),
+    ("Justfile", "Makefile"),
+    ("ts", "JavaScript"),
+    ("tsx", "JavaScript"),
+    ("jsx", "JavaScript"),
+    ("mjs", "JavaScript"),
+    ("cjs", "JavaScript"),
+    ("jsonc", "JSON"),
+    ("svelte", "HTML"),
+    ("vue", "HTML"),
+];
+
+/// The built-in and custom syntaxes, loaded on first use
+static SYNTAXES: RwLock<Option<Arc<SyntaxSet>>> = RwLock::new(None);
+
+/// All available syntaxes
+pub fn syntaxes() -> Arc<SyntaxSet> {
+    if let Some(syntaxes) = &*SYNTAXES.read().unwrap_or_else(PoisonError::into_inner) {
+        return syntaxes.clone();
+    }
+    reload()
+}
+
+/// Loads the custom syntaxes again, e.g. after one was added
+pub fn reload() -> Arc<SyntaxSet> {
+    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+
+    let dir = config::config_dir().map(|dir| dir.join("syntaxes"));
+    if let Some(entries) = dir.and_then(|dir| fs::read_dir(dir).ok()) {
+        let mut paths = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().unwrap_or_default() == "sublime-syntax")
+            .collect::<Vec<_>>();
+        paths.sort();
+        for path in paths {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let definition = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| {
+                    SyntaxDefinition::load_from_str(&content, true, Some(&name))
+                        .map_err(|e| e.to_string())
+                });
+            match definition {
+                Ok(definition) => builder.add(definition),
+                Err(e) => error!("Invalid syntax {path:?}: {e}"),
+            }
+        }
+    }
+
+    let syntaxes = Arc::new(builder.build());
+    *SYNTAXES.write().unwrap_or_else(PoisonError::into_inner) = Some(syntaxes.clone());
+    syntaxes
+}
+
+/// Detects the language of the file at `path` starting with `first_line`
+///
+/// The `rules` of the config, mapping file name patterns to a language, come first. Then the
+/// file name, the extension and the first line, e.g. a shebang, are checked.
+pub fn detect<'a>(
+    syntaxes: &'a SyntaxSet,
+    rules: &BTreeMap<String, String>,
+    path: &Path,
+    first_line: &str,
+) -> &'a SyntaxReference {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let by_rule = rules
+        .iter()
+        .filter(|(pattern, _)| matches_pattern(pattern, &file_name))
+        .find_map(|(_, name)| find(syntaxes, name));
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let by_file_name = || syntaxes.find_syntax_by_extension(&file_name);
+    let by_extension = || syntaxes.find_syntax_by_extension(&extension);
+    let by_first_line = || syntaxes.find_syntax_by_first_line(first_line);
+    let by_fallback = || {
+        FALLBACKS
+            .iter()
+            .filter(|(known, _)| *known == file_name || *known == extension)
+            .find_map(|(_, name)| syntaxes.find_syntax_by_name(name))
+    };
+
+    by_rule
+        .or_else(by_file_name)
+        .or_else(by_extension)
+        .or_else(by_first_line)
+        .or_else(by_fallback)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+}
+
+/// The syntax named `name`, ignoring the case
+pub fn find<'a>(syntaxes: &'a SyntaxSet, name: &str) -> Option<&'a SyntaxReference> {
+    syntaxes
+        .syntaxes()
+        .iter()
+        .find(|syntax| syntax.name.eq_ignore_ascii_case(name))
+}
+
+/// Whether `file_name` matches `pattern`, where `*` matches any amount of characters
+fn matches_pattern(pattern: &str, file_name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = file_name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(last) = parts.pop() else {
+        // no `*` at all
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
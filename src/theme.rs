@@ -0,0 +1,132 @@
+//! Themes of the syntax highlighting, also used for the general styling.
+//!
+//! Besides the built-in themes, every `.tmTheme` file in the `themes` directory of the config
+//! directory (`~/.config/omega/themes/`) is available, named after its file name.
+
+use std::{
+    fs,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use cursive::{
+    reexports::log::error,
+    theme::{Color, Effect, Effects, PaletteColor, Style},
+    Cursive,
+};
+use syntect::highlighting::{self, FontStyle, Theme, ThemeSet};
+
+use crate::{
+    config,
+    error::{Error, Result},
+    ui::edit_area::EditArea,
+};
+
+/// The theme used if no other one was chosen.
+pub const DEFAULT_THEME: &str = "base16-eighties.dark";
+
+/// The built-in and custom themes, loaded on first use
+static THEMES: RwLock<Option<Arc<ThemeSet>>> = RwLock::new(None);
+
+/// All available themes
+pub fn themes() -> Arc<ThemeSet> {
+    if let Some(themes) = &*THEMES.read().unwrap_or_else(PoisonError::into_inner) {
+        return themes.clone();
+    }
+    reload()
+}
+
+/// Loads the custom themes again, e.g. after one was added
+pub fn reload() -> Arc<ThemeSet> {
+    let mut themes = ThemeSet::load_defaults();
+
+    let dir = config::config_dir().map(|dir| dir.join("themes"));
+    if let Some(entries) = dir.and_then(|dir| fs::read_dir(dir).ok()) {
+        for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+            if path.extension().unwrap_or_default() != "tmTheme" {
+                continue;
+            }
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            match ThemeSet::get_theme(&path) {
+                Ok(theme) => {
+                    themes.themes.insert(name.to_string(), theme);
+                }
+                Err(e) => error!("Invalid theme {path:?}: {e}"),
+            }
+        }
+    }
+
+    let themes = Arc::new(themes);
+    *THEMES.write().unwrap_or_else(PoisonError::into_inner) = Some(themes.clone());
+    themes
+}
+
+/// Switches to the theme named `name` and saves it in the config
+pub fn choose(siv: &mut Cursive, name: &str) -> Result<()> {
+    set(siv, name)?;
+    config::save_theme(name)
+}
+
+/// Switches to the theme named `name`
+pub fn set(siv: &mut Cursive, name: &str) -> Result<()> {
+    let themes = themes();
+    let theme = themes
+        .themes
+        .get(name)
+        .ok_or_else(|| Error::Arguments(format!("There is no theme named \"{name}\"")))?;
+    apply(siv, theme);
+    Ok(())
+}
+
+/// Applies `theme` to the general styling and the highlighting of the editor
+pub fn apply(siv: &mut Cursive, theme: &Theme) {
+    siv.with_theme(|t| {
+        t.shadow = false;
+        if let Some(background) = theme.settings.background.map(translate_color) {
+            t.palette[PaletteColor::Background] = background;
+            t.palette[PaletteColor::View] = background;
+        }
+        if let Some(foreground) = theme.settings.foreground.map(translate_color) {
+            t.palette[PaletteColor::Primary] = foreground;
+            t.palette[PaletteColor::Secondary] = foreground;
+            t.palette[PaletteColor::Tertiary] = foreground;
+            t.palette[PaletteColor::TitlePrimary] = foreground;
+            t.palette[PaletteColor::TitleSecondary] = foreground;
+        }
+
+        if let Some(highlight) = theme.settings.highlight.map(translate_color) {
+            t.palette[PaletteColor::Highlight] = highlight;
+            t.palette[PaletteColor::HighlightText] = highlight;
+        }
+    });
+
+    siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        edit_area.set_theme(theme);
+    });
+}
+
+/// Translates a color of a theme to the one of the general styling
+pub fn translate_color(color: highlighting::Color) -> Color {
+    Color::Rgb(color.r, color.g, color.b)
+}
+
+/// Translates a style of the highlighting to the one of the general styling
+pub fn translate_style(style: highlighting::Style) -> Style {
+    let mut effects = Effects::empty();
+    for (font_style, effect) in [
+        (FontStyle::BOLD, Effect::Bold),
+        (FontStyle::UNDERLINE, Effect::Underline),
+        (FontStyle::ITALIC, Effect::Italic),
+    ] {
+        if style.font_style.contains(font_style) {
+            effects.insert(effect);
+        }
+    }
+    Style {
+        color: (
+            translate_color(style.foreground),
+            translate_color(style.background),
+        )
+            .into(),
+        effects,
+    }
+}
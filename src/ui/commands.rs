@@ -0,0 +1,650 @@
+//! The registry of all commands
+//!
+//! Every command is listed once in [`COMMANDS`], from which the commands of the Quick Access
+//! view, the info view and the global keybindings are generated.
+//!
+//! In the Quick Access view arguments can be passed after the command name, separated by
+//! whitespace (e.g. `>rename a.rs b.rs`), arguments containing spaces are quoted with `"`.
+//! Commands called without their arguments fall back to a dialog asking for them.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use cursive::{
+    event::{Event, Key},
+    Cursive,
+};
+
+use crate::{
+    app::State,
+    config,
+    error::{Error, Result, ResultExt},
+    indent::Indent,
+    syntax, theme,
+    ui::{
+        edit_area::EditArea,
+        keymap::{event_name, Keymap},
+        project_search, quick_access, search_bar, theme_picker, update_indent, update_language,
+        update_wrap,
+    },
+};
+
+/// Max amount of completions of an argument.
+const MAX_COMPLETIONS: usize = 100;
+
+/// The indentation styles of `>indentation`
+const TABS: &str = "tabs";
+const SPACES: &str = "spaces";
+
+/// A command which can be run via the Quick Access view and optionally a keybinding
+pub struct Command {
+    /// The name typed in after the `>` in the Quick Access view
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    /// The arguments the command takes, all of them are optional
+    pub args: &'static [Arg],
+    /// The default global keybinding running the command
+    pub keybinding: Option<Event>,
+    /// Runs the command with the given arguments
+    pub run: fn(&mut Cursive, &[&str]) -> Result<()>,
+}
+
+/// An argument of a command
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+}
+
+/// The kind of an argument, which determines how it's completed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgKind {
+    /// A file or directory, relative to the project, absolute or starting with `~`
+    Path,
+    /// A position in the current file, `line[:column]` starting at 1
+    Position,
+    /// The name of a theme
+    Theme,
+    /// The name of a language or `auto`
+    Language,
+    /// An indentation style, `tabs`, `spaces` or `auto`
+    Indent,
+    /// A width in columns
+    Width,
+}
+
+const PATH: Arg = Arg {
+    name: "path",
+    kind: ArgKind::Path,
+};
+
+/// All commands in the order they are listed
+pub const COMMANDS: &[Command] = &[
+    Command {
+        id: "quick_access",
+        title: "Open Quick Access",
+        description: "Fuzzy find files of the project or run a command",
+        args: &[],
+        keybinding: Some(Event::CtrlChar('p')),
+        run: |siv, _| quick_access::new(siv),
+    },
+    Command {
+        id: "find",
+        title: "Find in File",
+        description: "Search the opened file",
+        args: &[],
+        keybinding: Some(Event::CtrlChar('f')),
+        run: |siv, _| {
+            search_bar::open(siv);
+            Ok(())
+        },
+    },
+    Command {
+        id: "find_replace",
+        title: "Replace in File",
+        description: "Search and replace in the opened file",
+        args: &[],
+        keybinding: Some(Event::CtrlChar('r')),
+        run: |siv, _| {
+            search_bar::open_replace(siv);
+            Ok(())
+        },
+    },
+    Command {
+        id: "close",
+        title: "Close current View",
+        description: "Close the view in the foreground",
+        args: &[],
+        keybinding: Some(Event::Key(Key::Esc)),
+        run: |siv, _| {
+            if siv.screen().len() > 1 {
+                siv.pop_layer();
+            }
+            Ok(())
+        },
+    },
+    Command {
+        id: "debug",
+        title: "Open Debugger",
+        description: "Show the log messages",
+        args: &[],
+        keybinding: None,
+        run: |siv, _| quick_access::debug(siv),
+    },
+    Command {
+        id: "info",
+        title: "Open Infos",
+        description: "Show the version and all commands and keybindings",
+        args: &[],
+        keybinding: None,
+        run: |siv, _| quick_access::info(siv),
+    },
+    Command {
+        id: "open",
+        title: "Opening a File/Project",
+        description: "Open another file or directory as project",
+        args: &[PATH],
+        keybinding: None,
+        run: |siv, args| match args {
+            [path, ..] => {
+                let path = resolve(siv, path);
+                quick_access::open_path(siv, path)
+            }
+            [] => quick_access::open_project(siv),
+        },
+    },
+    Command {
+        id: "save",
+        title: "Saving the current opened File",
+        description: "Write the opened file to disk",
+        args: &[],
+        keybinding: None,
+        run: |siv, _| quick_access::save(siv, None),
+    },
+    Command {
+        id: "search",
+        title: "Searching in all Files",
+        description: "Search all files of the project",
+        args: &[],
+        keybinding: None,
+        run: |siv, _| {
+            project_search::open(siv);
+            Ok(())
+        },
+    },
+    Command {
+        id: "replace",
+        title: "Replacing in all Files",
+        description: "Search and replace in all files of the project",
+        args: &[],
+        keybinding: None,
+        run: |siv, _| {
+            project_search::open_replace(siv);
+            Ok(())
+        },
+    },
+    Command {
+        id: "new",
+        title: "Creating a new File/Directory",
+        description: "Create a file, or a directory if the path ends with `/`",
+        args: &[PATH],
+        keybinding: None,
+        run: |siv, args| match args {
+            [path, ..] => {
+                let new_path = resolve(siv, path);
+                if path.ends_with('/') {
+                    quick_access::create_dir(siv, &new_path)
+                } else {
+                    quick_access::create_file(siv, &new_path)
+                }
+            }
+            [] => quick_access::new_file(siv),
+        },
+    },
+    Command {
+        id: "rename",
+        title: "Renaming a File/Directory",
+        description: "Rename or move a file or directory, keeping its unsaved changes",
+        args: &[
+            Arg {
+                name: "from",
+                kind: ArgKind::Path,
+            },
+            Arg {
+                name: "to",
+                kind: ArgKind::Path,
+            },
+        ],
+        keybinding: None,
+        run: |siv, args| match args {
+            [from, to, ..] => {
+                let (from, to) = (resolve(siv, from), resolve(siv, to));
+                quick_access::rename_path(siv, &from, &to)
+            }
+            [from] => {
+                let from = resolve(siv, from);
+                quick_access::rename_file(siv, Some(from))
+            }
+            [] => quick_access::rename_file(siv, None),
+        },
+    },
+    Command {
+        id: "delete",
+        title: "Deleting a File/Directory",
+        description: "Delete a file or directory recursively after confirming, without a bin",
+        args: &[PATH],
+        keybinding: None,
+        run: |siv, args| match args {
+            [path, ..] => {
+                let path = resolve(siv, path);
+                quick_access::delete_file(siv, Some(path))
+            }
+            [] => quick_access::delete_file(siv, None),
+        },
+    },
+    Command {
+        id: "goto",
+        title: "Going to a Line",
+        description: "Move the cursor to a line and optionally a column",
+        args: &[Arg {
+            name: "line:column",
+            kind: ArgKind::Position,
+        }],
+        keybinding: Some(Event::CtrlChar('g')),
+        run: |siv, args| match args {
+            [position, ..] => goto(siv, position),
+            [] => quick_access::open_with(siv, ">goto "),
+        },
+    },
+    Command {
+        id: "theme",
+        title: "Changing the Theme",
+        description: "Pick a theme for the highlighting and the general styling, previewing it",
+        args: &[Arg {
+            name: "name",
+            kind: ArgKind::Theme,
+        }],
+        keybinding: None,
+        run: |siv, args| match args {
+            [name, ..] => theme::choose(siv, name),
+            [] => {
+                theme_picker::new(siv);
+                Ok(())
+            }
+        },
+    },
+    Command {
+        id: "language",
+        title: "Changing the Language",
+        description: "Highlight the current file as another language, `auto` detects it again",
+        args: &[Arg {
+            name: "name",
+            kind: ArgKind::Language,
+        }],
+        keybinding: None,
+        run: |siv, args| match args {
+            [name, ..] => set_language(siv, name),
+            [] => quick_access::open_with(siv, ">language "),
+        },
+    },
+    Command {
+        id: "indentation",
+        title: "Changing the Indentation",
+        description: "Convert the indentation of the current file to tabs or spaces of a width, \
+            `auto` detects it again",
+        args: &[
+            Arg {
+                name: "style",
+                kind: ArgKind::Indent,
+            },
+            Arg {
+                name: "width",
+                kind: ArgKind::Width,
+            },
+        ],
+        keybinding: None,
+        run: |siv, args| match args {
+            [style, width, ..] => set_indent(siv, style, Some(width)),
+            [style] => set_indent(siv, style, None),
+            [] => quick_access::open_with(siv, ">indentation "),
+        },
+    },
+    Command {
+        id: "wrap",
+        title: "Toggling Soft Wrap",
+        description: "Wrap the long lines of the current file at the width of the editor, \
+            or scroll horizontally again",
+        args: &[],
+        keybinding: Some(Event::AltChar('z')),
+        run: |siv, _| toggle_wrap(siv),
+    },
+    Command {
+        id: "quit",
+        title: "Quitting",
+        description: "Quit, asking to save unsaved changes",
+        args: &[],
+        keybinding: None,
+        run: |siv, _| quick_access::quit(siv),
+    },
+];
+
+/// The command with the given `id`
+pub fn get(id: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.id == id)
+}
+
+/// Runs the command with the given `id`, showing errors as dialog
+pub fn run(siv: &mut Cursive, id: &str, args: &[&str]) {
+    if let Some(command) = get(id) {
+        (command.run)(siv, args).handle(siv);
+    }
+}
+
+/// Registers the keybindings of all commands as global callbacks, replacing those of `old`
+pub fn set_global_callbacks(siv: &mut Cursive, old: &Keymap, keymap: &Keymap) {
+    for command in COMMANDS {
+        if let Some(event) = old.command(command) {
+            siv.clear_global_callbacks(event);
+        }
+    }
+    for command in COMMANDS {
+        if let Some(event) = keymap.command(command) {
+            siv.clear_global_callbacks(event.clone());
+            siv.add_global_callback(event, move |siv| run(siv, command.id, &[]));
+        }
+    }
+}
+
+impl Command {
+    /// The configured keybinding in a readable form, e.g. `Ctrl + p`
+    pub fn shortcut(&self) -> Option<String> {
+        config::get().keymap.command(self).as_ref().map(event_name)
+    }
+
+    /// How the command is used, e.g. `rename <from> <to>`
+    pub fn usage(&self) -> String {
+        self.args.iter().fold(self.id.to_string(), |usage, arg| {
+            format!("{usage} <{}>", arg.name)
+        })
+    }
+}
+
+/// Splits the input of the Quick Access view after the `>` into the command id and its arguments
+///
+/// Trailing whitespace starts a new, empty argument, which is the one to be completed.
+pub fn split_args(input: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    match current {
+        Some(arg) => args.push(arg),
+        // a new argument was started
+        None if !args.is_empty() => args.push(String::new()),
+        None => {}
+    }
+    args
+}
+
+/// Joins `args` to the input of the Quick Access view, quoting those containing whitespace
+pub fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("\"{arg}\"")
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Completions of the argument `value` of the kind `kind`
+pub fn complete(kind: ArgKind, value: &str, project_path: &Path) -> Vec<String> {
+    match kind {
+        ArgKind::Path => complete_path(value, project_path),
+        ArgKind::Position | ArgKind::Width => Vec::new(),
+        ArgKind::Theme => {
+            let value = value.to_lowercase();
+            theme::themes()
+                .themes
+                .keys()
+                .filter(|name| name.to_lowercase().contains(&value))
+                .cloned()
+                .collect()
+        }
+        ArgKind::Language => {
+            let value = value.to_lowercase();
+            let syntaxes = syntax::syntaxes();
+            let mut names = syntaxes
+                .syntaxes()
+                .iter()
+                .filter(|syntax| !syntax.hidden)
+                .map(|syntax| syntax.name.as_str())
+                .collect::<Vec<_>>();
+            names.sort_by_key(|name| name.to_lowercase());
+            [syntax::AUTO]
+                .into_iter()
+                .chain(names)
+                .filter(|name| name.to_lowercase().contains(&value))
+                .map(str::to_string)
+                .collect()
+        }
+        ArgKind::Indent => [TABS, SPACES, syntax::AUTO]
+            .into_iter()
+            .filter(|style| style.starts_with(&value.to_lowercase()))
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+/// The entries of the directory of `value` starting with its file name, directories first
+///
+/// The completions keep the directory as typed, directories end with a `/`.
+fn complete_path(value: &str, project_path: &Path) -> Vec<String> {
+    let (dir, prefix) = match value.rfind('/') {
+        Some(i) => value.split_at(i + 1),
+        None => ("", value),
+    };
+    let Ok(entries) = fs::read_dir(resolve_path(dir, project_path)) else {
+        return Vec::new();
+    };
+
+    let mut entries = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // hidden entries only if asked for
+            let visible = !name.starts_with('.') || prefix.starts_with('.');
+            (visible && name.starts_with(prefix)).then(|| (entry.path().is_dir(), name))
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then_with(|| a.1.to_lowercase().cmp(&b.1.to_lowercase()))
+    });
+
+    entries
+        .into_iter()
+        .take(MAX_COMPLETIONS)
+        .map(|(is_dir, name)| format!("{dir}{name}{}", if is_dir { "/" } else { "" }))
+        .collect()
+}
+
+/// Resolves a path argument, relative paths are relative to the project
+pub fn resolve_path(arg: &str, project_path: &Path) -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_default();
+    if arg == "~" {
+        home
+    } else if let Some(rest) = arg.strip_prefix("~/") {
+        home.join(rest)
+    } else {
+        project_path.join(arg)
+    }
+}
+
+/// Resolves a path argument relative to the current project
+fn resolve(siv: &mut Cursive, arg: &str) -> PathBuf {
+    let project_path = siv
+        .with_user_data(|state: &mut State| state.project_path.clone())
+        .unwrap_or_default();
+    resolve_path(arg, &project_path)
+}
+
+/// Overrides the detected language of the current file, `auto` detects it again
+fn set_language(siv: &mut Cursive, name: &str) -> Result<()> {
+    let language = if name.eq_ignore_ascii_case(syntax::AUTO) {
+        None
+    } else {
+        let syntaxes = syntax::syntaxes();
+        let syntax = syntax::find(&syntaxes, name)
+            .ok_or_else(|| Error::Arguments(format!("There is no language named \"{name}\"")))?;
+        Some(syntax.name.clone())
+    };
+    siv.with_user_data(|state: &mut State| {
+        let path = state.current_file.clone()?;
+        state.files.get_mut(&path)?.language = language;
+        Some(())
+    })
+    .flatten()
+    .ok_or_else(|| Error::Arguments("There is no open file".to_string()))?;
+    update_language(siv);
+    Ok(())
+}
+
+/// Converts the indentation of the current file to `style`, `auto` detects it again
+///
+/// Spaces are `width` wide, by default as wide as configured.
+fn set_indent(siv: &mut Cursive, style: &str, width: Option<&str>) -> Result<()> {
+    let width = match width {
+        Some(width) => width
+            .parse::<usize>()
+            .ok()
+            .filter(|width| (1..=16).contains(width))
+            .ok_or_else(|| {
+                Error::Arguments(format!("\"{width}\" is not a width between 1 and 16"))
+            })?,
+        None => config::get().editor.tab_size,
+    };
+    let indent = match style.to_lowercase().as_str() {
+        TABS => Some(Indent::Tabs),
+        SPACES => Some(Indent::Spaces(width)),
+        syntax::AUTO => None,
+        _ => {
+            return Err(Error::Arguments(format!(
+                "\"{style}\" is not one of {TABS}, {SPACES} or {}",
+                syntax::AUTO
+            )))
+        }
+    };
+    siv.with_user_data(|state: &mut State| {
+        let path = state.current_file.clone()?;
+        state.files.get_mut(&path)?.indent = indent;
+        Some(())
+    })
+    .flatten()
+    .ok_or_else(|| Error::Arguments("There is no open file".to_string()))?;
+
+    match indent {
+        Some(indent) => {
+            if let Some(cb) = siv.call_on_name("editor", |edit_area: &mut EditArea| {
+                edit_area.convert_indent(indent)
+            }) {
+                cb(siv);
+            }
+        }
+        None => update_indent(siv),
+    }
+    Ok(())
+}
+
+/// Toggles wrapping the long lines of the current file
+fn toggle_wrap(siv: &mut Cursive) -> Result<()> {
+    siv.with_user_data(|state: &mut State| {
+        let path = state.current_file.clone()?;
+        let file = state.files.get_mut(&path)?;
+        file.wrap = !file.wrap;
+        Some(())
+    })
+    .flatten()
+    .ok_or_else(|| Error::Arguments("There is no open file".to_string()))?;
+    update_wrap(siv);
+    Ok(())
+}
+
+/// Moves the cursor to `position`, which is `line[:column]` starting at 1
+fn goto(siv: &mut Cursive, position: &str) -> Result<()> {
+    let invalid = || Error::Arguments(format!("\"{position}\" is not a valid line:column"));
+    let (line, column) = position.split_once(':').unwrap_or((position, "1"));
+    let line = line.parse::<usize>().map_err(|_| invalid())?;
+    let column = column.parse::<usize>().map_err(|_| invalid())?;
+
+    if let Some(cb) = siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        edit_area.goto(line.saturating_sub(1), column.saturating_sub(1))
+    }) {
+        cb(siv);
+    }
+    siv.focus_name("editor").ok();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_quoted_args() {
+        assert_eq!(split_args(""), Vec::<String>::new());
+        assert_eq!(split_args("goto"), ["goto"]);
+        assert_eq!(split_args("goto  12:3"), ["goto", "12:3"]);
+        assert_eq!(
+            split_args(r#"rename "my file.rs" new.rs"#),
+            ["rename", "my file.rs", "new.rs"]
+        );
+        // quotes only group, they can also appear inside of an argument
+        assert_eq!(split_args(r#"new a"b c"d"#), ["new", "ab cd"]);
+        assert_eq!(split_args(r#"new """#), ["new", ""]);
+        // an unclosed quote reaches to the end
+        assert_eq!(split_args(r#"new "a b "#), ["new", "a b "]);
+    }
+
+    #[test]
+    fn start_empty_argument() {
+        // trailing whitespace starts the argument to be completed
+        assert_eq!(split_args("open "), ["open", ""]);
+        assert_eq!(split_args("open src "), ["open", "src", ""]);
+    }
+
+    #[test]
+    fn join_quoted_args() {
+        let joined = join_args(&split_args(r#"rename "my file.rs" """#));
+        assert_eq!(joined, r#"rename "my file.rs" """#);
+        assert_eq!(split_args(&joined), ["rename", "my file.rs", ""]);
+    }
+
+    #[test]
+    fn resolve_paths() {
+        let project = Path::new("/project");
+        let home = dirs::home_dir().unwrap_or_default();
+        assert_eq!(resolve_path("src/a.rs", project), project.join("src/a.rs"));
+        assert_eq!(resolve_path("/etc", project), Path::new("/etc"));
+        assert_eq!(resolve_path("~", project), home);
+        assert_eq!(resolve_path("~/a", project), home.join("a"));
+    }
+}
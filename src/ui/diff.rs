@@ -0,0 +1,38 @@
+use cursive::{
+    theme::{BaseColor, Color, Effect, Style},
+    utils::markup::StyledString,
+    view::Scrollable,
+    views::{ScrollView, TextView},
+};
+use similar::{ChangeTag, TextDiff};
+
+/// Lines of unchanged context shown around each change.
+const CONTEXT: usize = 3;
+
+/// Creates a scrollable, line-based diff from `old` to `new` in the unified format
+pub fn new(old: &str, new: &str) -> ScrollView<TextView> {
+    let diff = TextDiff::from_lines(old, new);
+
+    let mut styled = StyledString::new();
+    for hunk in diff.unified_diff().context_radius(CONTEXT).iter_hunks() {
+        styled.append_styled(format!("{}\n", hunk.header()), Effect::Bold);
+        for change in hunk.iter_changes() {
+            let (sign, style) = match change.tag() {
+                ChangeTag::Delete => ("-", Style::from(Color::Dark(BaseColor::Red))),
+                ChangeTag::Insert => ("+", Style::from(Color::Dark(BaseColor::Green))),
+                ChangeTag::Equal => (" ", Style::none()),
+            };
+            let mut line = format!("{sign}{}", change.value());
+            if change.missing_newline() {
+                line.push('\n');
+            }
+            styled.append_styled(line, style);
+        }
+    }
+
+    if styled.is_empty() {
+        styled.append_plain("No differences");
+    }
+
+    TextView::new(styled).scrollable()
+}
@@ -7,10 +7,11 @@
 //! # Examples
 //!
 //! ```
-//! use cursive_core::traits::{Nameable, Resizable};
-//! use cursive_core::views::EditArea;
+//! use cursive::traits::{Nameable, Resizable};
+//! use omega::{theme, ui::edit_area::EditArea};
 //!
-//! let edit_area = EditArea::new()
+//! let theme = &theme::themes().themes[theme::DEFAULT_THEME];
+//! let edit_area = EditArea::new(theme)
 //!     .content("Write description here...")
 //!     .with_name("edit_area")
 //!     .fixed_width(30)
@@ -19,10 +20,10 @@
 
 use cursive::{
     direction::Direction,
-    event::{Callback, Event, EventResult, Key, MouseEvent},
+    event::{Callback, Event, EventResult, Key, MouseButton, MouseEvent},
     impl_enabled,
     reexports::log::error,
-    theme::{BaseColor, Color, ColorStyle, Effect, PaletteColor, PaletteStyle, Style},
+    theme::{BaseColor, Color, ColorStyle, ColorType, Effect, PaletteColor, PaletteStyle, Style},
     utils::{markup::StyledString, span::SpannedString},
     view::CannotFocus,
     Cursive, Printer, Rect, Vec2, View, With, XY,
@@ -33,29 +34,100 @@ use cursive::{
 };
 use ropey::{Rope, RopeSlice};
 use std::{
-    cmp::{max, min},
-    sync::Arc,
+    cmp::{max, min, Reverse},
+    collections::HashMap,
+    iter, mem,
+    ops::Range,
+    sync::{Arc, Mutex},
 };
 use syntect::{
-    easy::HighlightLines,
     highlighting::Theme,
     parsing::{SyntaxReference, SyntaxSet},
 };
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
+use crate::{
+    backend,
+    buffer::SharedBuffer,
+    error::ResultExt,
+    highlighter::Highlighter,
+    history::{EditKind, Snapshot},
+    indent::Indent,
+    search::{Edit, Search},
+    syntax, theme,
+    ui::{
+        commands,
+        keymap::{Action, Keymap},
+        quick_access,
+        vim::{Command, InsertAt, Mode, Motion, Operator, Parsed, Vim},
+    },
+    wrap::Wrap,
+};
+
+/// The opening and closing brackets, used for indenting, auto-closing and matching.
+///
+/// Angle brackets are left out, they are mostly comparison and shift operators.
+const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+/// The quotes closed automatically.
+const QUOTES: &[char] = &['"', '\'', '`'];
+
+/// Lines searched for a matching bracket, in each direction.
+const MATCH_LINES: usize = 1000;
+
+/// Columns of the vertical scrollbar and its padding, left out when wrapping lines.
+const SCROLLBAR_WIDTH: usize = 2;
+
 /// Closure type for callbacks when something happens, for example the content is modified.
 ///
 /// Arguments are the `Cursive`, current content of the input and cursor
 /// position
 pub type OnChange = dyn Fn(&mut Cursive, &Rope, Vec2, Cursor) + Send + Sync;
 
+/// Closure type for callbacks when the mode of the modal editing changes.
+pub type OnModeChange = dyn Fn(&mut Cursive, Mode) + Send + Sync;
+
+/// Runs `first` and afterwards `second`.
+fn chain(first: Callback, second: Callback) -> Callback {
+    Callback::from_fn(move |siv| {
+        first(siv);
+        second(siv);
+    })
+}
+
+/// Groups characters into words, punctuation and whitespace for the word motions.
+fn char_class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// Terminal columns of `grapheme` at column `x`, a tab reaches to the next tab stop.
+pub fn grapheme_width(grapheme: &str, x: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        tab_width - x % tab_width
+    } else {
+        UnicodeWidthStr::width(grapheme)
+    }
+}
+
+/// Terminal columns of the text `s` of a single line.
+fn line_width(s: &str, tab_width: usize) -> usize {
+    s.graphemes(true)
+        .fold(0, |x, grapheme| x + grapheme_width(grapheme, x, tab_width))
+}
+
 /// Computes how many characters (from the start of `s`) take up at most `max_width` terminal columns.
-fn grapheme_prefix_length(s: &str, max_width: usize) -> usize {
+fn grapheme_prefix_length(s: &str, max_width: usize, tab_width: usize) -> usize {
     let mut current_width = 0;
     let mut char_count = 0;
     for grapheme in s.graphemes(true) {
-        let grapheme_width = UnicodeWidthStr::width(grapheme);
+        let grapheme_width = grapheme_width(grapheme, current_width, tab_width);
         if current_width + grapheme_width > max_width {
             break;
         }
@@ -65,6 +137,31 @@ fn grapheme_prefix_length(s: &str, max_width: usize) -> usize {
     char_count
 }
 
+/// The character offset of the first grapheme boundary in `s` after `offset`.
+fn next_grapheme_boundary(s: &str, offset: usize) -> usize {
+    let mut boundary = 0;
+    for grapheme in s.graphemes(true) {
+        boundary += grapheme.chars().count();
+        if boundary > offset {
+            break;
+        }
+    }
+    boundary
+}
+
+/// The character offset of the last grapheme boundary in `s` before `offset`.
+fn prev_grapheme_boundary(s: &str, offset: usize) -> usize {
+    let mut boundary = 0;
+    for grapheme in s.graphemes(true) {
+        let next = boundary + grapheme.chars().count();
+        if next >= offset {
+            break;
+        }
+        boundary = next;
+    }
+    boundary
+}
+
 /// Swaps `i`-th with `j`-th line in `rope`
 fn swap_lines(rope: &mut Rope, i: usize, j: usize) {
     if i == j {
@@ -100,17 +197,51 @@ fn special_character(grapheme: &str) -> Option<&str> {
     }
 }
 
+/// Whether `open` and `close` are a pair of brackets or quotes which is closed automatically.
+fn is_pair(open: char, close: char) -> bool {
+    BRACKETS.contains(&(open, close)) || (open == close && QUOTES.contains(&open))
+}
+
+/// The characters selected by a cursor with its selection anchor, empty without a selection.
+fn selected((cursor, anchor): (Cursor, Option<usize>)) -> Range<usize> {
+    let anchor = anchor.unwrap_or(cursor.char_offset);
+    min(anchor, cursor.char_offset)..max(anchor, cursor.char_offset)
+}
+
 /// The cursor offset
 #[derive(Clone, Copy, Debug, Default)]
 pub struct Cursor {
     /// Vertical rows from top, rows are separated with a `\n`
     pub row: usize,
-    /// From left to right
+    /// Terminal column from left to right, tabs reach to the next tab stop
     pub column: usize,
     /// Byte offset of the currently selected text
     pub byte_offset: usize,
     /// Character offset of the currently selected text
     pub char_offset: usize,
+    /// Terminal column kept while moving up and down over shorter lines, `None` if it's `column`
+    pub sticky_column: Option<usize>,
+}
+
+impl Cursor {
+    /// Creates the `Cursor` at `char_offset` in `content`, clamped to its end
+    ///
+    /// The column is the one on screen, with tabs reaching to the next multiple of `tab_width`.
+    pub fn from_char_offset(content: &Rope, char_offset: usize, tab_width: usize) -> Self {
+        let char_offset = char_offset.min(content.len_chars());
+        let row = content.char_to_line(char_offset);
+        let line_start = content.line_to_char(row);
+        Self {
+            row,
+            column: line_width(
+                &content.slice(line_start..char_offset).to_string(),
+                tab_width,
+            ),
+            byte_offset: content.char_to_byte(char_offset),
+            char_offset,
+            sticky_column: None,
+        }
+    }
 }
 
 pub struct EditArea {
@@ -123,15 +254,18 @@ pub struct EditArea {
     /// Width of the longest line
     max_content_width: usize,
 
-    /// Specified through file extension, the applied highlighting
+    /// The detected or chosen language, the applied highlighting
     synref: SyntaxReference,
 
     /// Syntax Set
-    syntax: SyntaxSet,
+    syntax: Arc<SyntaxSet>,
 
     /// Current Theme for highlighting
     theme: Theme,
 
+    /// Cached highlighting of the content
+    highlighter: Mutex<Highlighter>,
+
     /// When `false`, we don't take any input.
     enabled: bool,
 
@@ -150,11 +284,58 @@ pub struct EditArea {
     /// Will be called with the current content and the cursor position.
     on_edit: Option<Arc<OnChange>>,
 
+    /// Callback when the mode of the modal editing changes.
+    on_mode: Option<Arc<OnModeChange>>,
+
     /// Base for scrolling features
     scroll_core: scroll::Core,
 
     /// Cursor offset view the `struct::Cursor` for further details
     cursor: Cursor,
+
+    /// Character offset of the selection anchor, the selection spans from here to the cursor
+    selection: Option<usize>,
+
+    /// Further cursors of the multi-cursor editing with their selection anchors
+    cursors: Vec<(Cursor, Option<usize>)>,
+
+    /// The edited buffer, shared with the global state
+    ///
+    /// The `content` is written to it after every edit, its history is edited directly.
+    buffer: SharedBuffer,
+
+    /// Active search, its matches are highlighted
+    search: Option<Search>,
+    /// The bytes edited since the matches of the search were updated
+    edit: Option<Edit>,
+
+    /// The editor actions by their keybinding
+    keymap: HashMap<Event, Action>,
+
+    /// Columns between the tab stops
+    tab_width: usize,
+
+    /// Indentation of the current buffer, inserted by indenting
+    indent: Indent,
+
+    /// Whether line numbers are shown in front of the lines
+    line_numbers: bool,
+
+    /// Whether long lines are wrapped at the width of the view instead of scrolling horizontally
+    soft_wrap: bool,
+
+    /// Cached wrapping of the content, only used with `soft_wrap`
+    wrap: Mutex<Wrap>,
+
+    /// Whether brackets and quotes are closed automatically
+    auto_pairs: bool,
+
+    /// Character offsets of the automatically inserted closing brackets and quotes, typing them
+    /// steps over them
+    auto_closed: Vec<usize>,
+
+    /// State of the modal editing, `None` if it's disabled
+    vim: Option<Vim>,
 }
 
 impl_scroller!(EditArea::scroll_core);
@@ -164,20 +345,37 @@ impl EditArea {
 
     /// Creates a new, empty EditArea with a specified theme.
     pub fn new(theme: &Theme) -> Self {
-        let syntax = SyntaxSet::load_defaults_newlines();
+        let syntax = syntax::syntaxes();
+        let synref = syntax.find_syntax_plain_text().clone();
         EditArea {
             content: Rope::new(),
             max_line_index: 0,
             max_content_width: 0,
-            synref: syntax.find_syntax_plain_text().clone(),
+            highlighter: Mutex::new(Highlighter::new(&synref)),
+            synref,
             syntax,
             theme: theme.clone(),
             enabled: true,
             on_interact: None,
             on_scroll: None,
             on_edit: None,
+            on_mode: None,
             scroll_core: scroll::Core::new(),
             cursor: Cursor::default(),
+            selection: None,
+            cursors: Vec::new(),
+            buffer: SharedBuffer::default(),
+            search: None,
+            edit: None,
+            keymap: Keymap::default().editor(),
+            tab_width: 4,
+            indent: Indent::default(),
+            line_numbers: true,
+            soft_wrap: false,
+            wrap: Mutex::default(),
+            auto_pairs: true,
+            auto_closed: Vec::new(),
+            vim: None,
         }
         .with(|area| {
             // Enable scrolling in x direction
@@ -190,7 +388,7 @@ impl EditArea {
     }
 
     /// Retrieves the content of the view.
-    pub fn get_content(&self) -> RopeSlice {
+    pub fn get_content(&self) -> RopeSlice<'_> {
         self.content.slice(..)
     }
 
@@ -229,6 +427,27 @@ impl EditArea {
         self.on_interact_callback().unwrap_or(Callback::dummy())
     }
 
+    /// Moves the cursor to `row` and `column`, both clamped to the content
+    pub fn goto(&mut self, row: usize, column: usize) -> Callback {
+        let row = row.min(self.content.len_lines().saturating_sub(1));
+        let start = self.content.line_to_char(row);
+        let line = self.content.line(row);
+        let len = line.len_chars() - usize::from(line.chars().last() == Some('\n'));
+
+        self.buffer.lock().unwrap().history.seal();
+        self.selection = None;
+        self.cursors.clear();
+        self.set_cursor_from_char_offset(start + column.min(len));
+        self.scroll_to_cursor()
+    }
+
+    /// Scrolls the cursor into view, needed if it's moved outside of an event
+    fn scroll_to_cursor(&mut self) -> Callback {
+        let important_area = self.inner_important_area(self.scroll_core.inner_size());
+        self.scroll_core.scroll_to_rect(important_area);
+        self.on_interact_callback().unwrap_or(Callback::dummy())
+    }
+
     /// Sets the `Cursor` from a given `byte_offset`
     fn set_cursor_from_char_offset(&mut self, char_offset: usize) -> Callback {
         let byte_offset = self.content.char_to_byte(char_offset);
@@ -237,6 +456,7 @@ impl EditArea {
             column: self.col_at(byte_offset),
             byte_offset,
             char_offset,
+            sticky_column: None,
         })
     }
 
@@ -247,30 +467,39 @@ impl EditArea {
             column: self.col_at(byte_offset),
             byte_offset,
             char_offset: self.content.byte_to_char(byte_offset),
-        })
-    }
-
-    /// Only updates the offset from the `byte_offset`
-    fn set_offset(&mut self, byte_offset: usize) -> Callback {
-        self.set_cursor(Cursor {
-            row: self.cursor.row,
-            column: self.cursor.column,
-            byte_offset,
-            char_offset: self.content.byte_to_char(byte_offset),
+            sticky_column: None,
         })
     }
 
     /// Sets the content of the view.
     pub fn set_content<S: Into<String>>(&mut self, content: S) -> Callback {
-        self.content = content.into().into();
+        self.replace_content(content.into().into());
+        self.on_edit_callback().unwrap_or_else(Callback::dummy)
+    }
+
+    /// Edits `buffer` from now on, showing its content.
+    pub fn set_buffer(&mut self, buffer: SharedBuffer) {
+        let content = buffer.lock().unwrap().content.clone();
+        self.buffer = buffer;
+        self.replace_content(content);
+    }
+
+    /// Replaces the content, keeping the cursor within it.
+    fn replace_content(&mut self, content: Rope) {
+        self.set_rope(content);
+        self.selection = None;
+        self.auto_closed.clear();
+        self.cursors.clear();
+        if let Some(vim) = self.vim.as_mut().filter(|vim| vim.mode.is_visual()) {
+            vim.set_mode(Mode::Normal);
+        }
+        self.invalidate_highlighting(0);
 
         // First, make sure we are within the bounds.
         self.set_curser_from_byte_offset(min(self.cursor.byte_offset, self.content.len_bytes()));
 
         // Recaulcuate the available width.
         self.compute_max_content_width(None);
-
-        self.on_edit_callback().unwrap_or_else(Callback::dummy)
     }
 
     /// Sets the content of the view.
@@ -282,13 +511,248 @@ impl EditArea {
         self
     }
 
-    /// Set highlighting style via a file extension
-    pub fn set_highlighting(&mut self, extension: &str) {
-        self.synref = self
-            .syntax
-            .find_syntax_by_extension(extension)
-            .cloned()
-            .unwrap_or(self.syntax.find_syntax_plain_text().clone());
+    /// Sets the active search and selects its first match from the cursor on.
+    ///
+    /// `None` removes the search and its highlighting.
+    pub fn set_search(&mut self, search: Option<Search>) -> Callback {
+        self.search = search;
+        let start = self
+            .selection_range()
+            .map_or(self.cursor.char_offset, |range| range.start);
+        let start = self.content.char_to_byte(start);
+
+        self.edit = None;
+        let next = self.search.as_mut().and_then(|search| {
+            search.find(&self.content);
+            search.next(start)
+        });
+        match next {
+            Some(index) => self.select_match(index),
+            None => Callback::dummy(),
+        }
+    }
+
+    /// Selects the next or previous match of the active search.
+    pub fn search_next(&mut self, forward: bool) -> Callback {
+        let byte_offset = self.cursor.byte_offset;
+        let index = self.search.as_ref().and_then(|search| {
+            if forward {
+                search.next(byte_offset + 1)
+            } else {
+                search.previous(byte_offset)
+            }
+        });
+        match index {
+            Some(index) => self.select_match(index),
+            None => Callback::dummy(),
+        }
+    }
+
+    /// Replaces the match at the cursor with `replacement` and selects the next one.
+    ///
+    /// If the cursor isn't at a match, only the next match is selected.
+    pub fn replace_next(&mut self, replacement: &str) -> Callback {
+        let Some(search) = &self.search else {
+            return Callback::dummy();
+        };
+        let Some(index) = search.position(self.cursor.byte_offset) else {
+            return self.search_next(true);
+        };
+        let range = search.matches()[index].clone();
+        let replacements = search
+            .replacements(&self.content, replacement)
+            .into_iter()
+            .filter(|(r, _)| *r == range)
+            .collect::<Vec<_>>();
+
+        let Some((_, replaced)) = replacements.first() else {
+            return Callback::dummy();
+        };
+        let end = range.start + replaced.len();
+        self.replace(&replacements);
+
+        let edit_callback = self.on_edit_callback();
+        let next = self.search.as_ref().and_then(|search| search.next(end));
+        if let Some(index) = next {
+            self.select_match(index);
+        }
+        edit_callback.unwrap_or(Callback::dummy())
+    }
+
+    /// Replaces all matches with `replacement` as a single edit.
+    pub fn replace_all(&mut self, replacement: &str) -> Callback {
+        let Some(search) = &self.search else {
+            return Callback::dummy();
+        };
+        let replacements = search.replacements(&self.content, replacement);
+        if replacements.is_empty() {
+            return Callback::dummy();
+        }
+        self.replace(&replacements);
+
+        self.on_edit_callback().unwrap_or(Callback::dummy())
+    }
+
+    /// Replaces the sorted byte ranges with their new text, recorded as one undo step.
+    fn replace(&mut self, replacements: &[(Range<usize>, String)]) {
+        self.buffer.lock().unwrap().history.seal();
+        self.record(EditKind::Other);
+        self.selection = None;
+        self.cursors.clear();
+
+        let cursor = self.cursor.byte_offset;
+        let mut new_cursor = cursor;
+        for (range, replaced) in replacements.iter().rev() {
+            let start = self.content.byte_to_char(range.start);
+            let end = self.content.byte_to_char(range.end);
+            self.remove_chars(start..end);
+            self.insert_chars(start, replaced);
+
+            // Keep the cursor at its position in the text around it.
+            if range.end <= cursor {
+                new_cursor = new_cursor + replaced.len() - range.len();
+            } else if range.start < cursor {
+                new_cursor = range.start;
+            }
+        }
+
+        if let Some((first, _)) = replacements.first() {
+            self.invalidate_highlighting(self.content.byte_to_line(first.start));
+        }
+        self.compute_max_content_width(None);
+        self.set_curser_from_byte_offset(min(new_cursor, self.content.len_bytes()));
+    }
+
+    /// Returns the index of the match at the cursor and the total amount of matches.
+    pub fn search_status(&self) -> Option<(Option<usize>, usize)> {
+        self.search.as_ref().map(|search| {
+            (
+                search.position(self.cursor.byte_offset),
+                search.matches().len(),
+            )
+        })
+    }
+
+    /// Selects the `index`-th match of the active search, placing the cursor at its start.
+    fn select_match(&mut self, index: usize) -> Callback {
+        let Some(range) = self
+            .search
+            .as_ref()
+            .map(|search| search.matches()[index].clone())
+        else {
+            return Callback::dummy();
+        };
+        self.buffer.lock().unwrap().history.seal();
+        self.cursors.clear();
+        self.selection = Some(self.content.byte_to_char(range.end));
+        self.set_curser_from_byte_offset(range.start);
+        self.scroll_to_cursor()
+    }
+
+    /// Sets the highlighting to `synref` of `syntax`
+    pub fn set_syntax(&mut self, syntax: Arc<SyntaxSet>, synref: &SyntaxReference) {
+        self.synref = synref.clone();
+        self.syntax = syntax;
+        *self.highlighter.get_mut().unwrap() = Highlighter::new(&self.synref);
+    }
+
+    /// The name of the language of the highlighting
+    pub fn language(&self) -> &str {
+        &self.synref.name
+    }
+
+    /// Sets the editor actions by their keybinding
+    pub fn set_keymap(&mut self, keymap: HashMap<Event, Action>) {
+        self.keymap = keymap;
+    }
+
+    /// Enables or disables the modal editing, starting in the normal mode
+    pub fn set_modal(&mut self, modal: bool) {
+        if modal != self.vim.is_some() {
+            self.vim = modal.then(Vim::default);
+            self.selection = None;
+            self.cursors.clear();
+        }
+    }
+
+    /// The mode of the modal editing, `None` if it's disabled
+    pub fn mode(&self) -> Option<Mode> {
+        self.vim.as_ref().map(|vim| vim.mode)
+    }
+
+    /// Sets the columns between the tab stops
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width;
+        self.cursor = self.cursor_at(self.cursor.char_offset);
+        self.compute_max_content_width(None);
+    }
+
+    /// The indentation of the current buffer
+    pub fn indent(&self) -> Indent {
+        self.indent
+    }
+
+    /// Sets the indentation of the current buffer, used by indenting
+    pub fn set_indent(&mut self, indent: Indent) {
+        self.indent = indent;
+    }
+
+    /// Converts the indentation of every line to `indent`, recorded as one undo step.
+    pub fn convert_indent(&mut self, indent: Indent) -> Callback {
+        let converted = self
+            .indent
+            .convert(self.content.slice(..), indent, self.tab_width);
+        self.indent = indent;
+        if self.content == converted.as_str() {
+            return Callback::dummy();
+        }
+        self.buffer.lock().unwrap().history.seal();
+        self.record(EditKind::Other);
+
+        let (row, column) = (self.cursor.row, self.cursor.column);
+        self.set_rope(converted.into());
+        self.selection = None;
+        self.cursors.clear();
+        self.invalidate_highlighting(0);
+        self.compute_max_content_width(None);
+        self.set_cursor_from_char_offset(self.offset_at_column(row, column));
+
+        self.on_edit_callback().unwrap_or_else(Callback::dummy)
+    }
+
+    /// Shows or hides the line numbers
+    pub fn set_line_numbers(&mut self, line_numbers: bool) {
+        self.line_numbers = line_numbers;
+        self.compute_max_content_width(None);
+    }
+
+    /// Whether long lines are wrapped
+    pub fn soft_wrap(&self) -> bool {
+        self.soft_wrap
+    }
+
+    /// Wraps long lines at the width of the view, or scrolls horizontally instead
+    pub fn set_soft_wrap(&mut self, soft_wrap: bool) -> Callback {
+        if self.soft_wrap == soft_wrap {
+            return Callback::dummy();
+        }
+        self.soft_wrap = soft_wrap;
+        // the sticky column is one of the rows of a wrapped line
+        self.cursor.sticky_column = None;
+        self.scroll_core.set_scroll_x(!soft_wrap);
+        self.set_scroll(Vec2::new(0, self.scroll().y));
+        self.scroll_to_cursor()
+    }
+
+    /// Enables or disables closing brackets and quotes automatically
+    pub fn set_auto_pairs(&mut self, auto_pairs: bool) {
+        self.auto_pairs = auto_pairs;
+    }
+
+    /// Set the theme of the highlighting
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+        *self.highlighter.get_mut().unwrap() = Highlighter::new(&self.synref);
     }
 
     /// Sets a callback to be called whenever the cursor is modified.
@@ -342,17 +806,58 @@ impl EditArea {
         self.on_edit = Some(Arc::new(callback));
     }
 
+    /// Sets a callback to be called whenever the mode of the modal editing changes.
+    pub fn set_on_mode<F>(&mut self, callback: F)
+    where
+        F: Fn(&mut Cursive, Mode) + 'static + Send + Sync,
+    {
+        self.on_mode = Some(Arc::new(callback));
+    }
+
     /// Finds the row containing the grapheme at the given offset
     fn row_at(&self, byte_offset: usize) -> usize {
         self.content.byte_to_line(byte_offset)
     }
 
+    /// Finds the terminal column of the grapheme at the given offset
     fn col_at(&self, byte_offset: usize) -> usize {
         let row_id = self.row_at(byte_offset);
         let start = self.content.line_to_char(row_id);
         let end = self.content.byte_to_char(byte_offset);
 
-        self.content.slice(start..end).len_chars()
+        line_width(&self.content.slice(start..end).to_string(), self.tab_width)
+    }
+
+    /// The `Cursor` at `char_offset`
+    fn cursor_at(&self, char_offset: usize) -> Cursor {
+        Cursor::from_char_offset(&self.content, char_offset, self.tab_width)
+    }
+
+    /// The character offset of the grapheme after the one at `offset`, the line break included.
+    fn next_grapheme(&self, offset: usize) -> usize {
+        if offset >= self.content.len_chars() {
+            return self.content.len_chars();
+        }
+        let row = self.content.char_to_line(offset);
+        let start = self.content.line_to_char(row);
+        start + next_grapheme_boundary(&self.content.line(row).to_string(), offset - start)
+    }
+
+    /// The character offset of the grapheme before `offset`, the line break included.
+    fn prev_grapheme(&self, offset: usize) -> usize {
+        if offset == 0 {
+            return 0;
+        }
+        let row = self.content.char_to_line(offset - 1);
+        let start = self.content.line_to_char(row);
+        start + prev_grapheme_boundary(&self.content.line(row).to_string(), offset - start)
+    }
+
+    /// The character offset at the terminal `column` of `row`, clamped to the line end.
+    fn offset_at_column(&self, row: usize, column: usize) -> usize {
+        let (start, end) = self.line_bounds(row);
+        let line = self.content.slice(start..end).to_string();
+        start + grapheme_prefix_length(&line, column, self.tab_width)
     }
 
     /// Finds the row containing the cursor
@@ -360,26 +865,148 @@ impl EditArea {
         self.row_at(self.cursor.byte_offset)
     }
 
-    /// Finds the col containing the cursor
-    fn selected_col(&self) -> usize {
-        self.col_at(self.cursor.byte_offset)
+    /// Inserts `text` at the character `offset`, keeping track of the edit for the search.
+    fn insert_chars(&mut self, offset: usize, text: &str) {
+        let start = self.content.char_to_byte(offset);
+        self.content.insert(offset, text);
+        self.edited(Edit {
+            start,
+            old_end: start,
+            new_end: start + text.len(),
+        });
+    }
+
+    /// Removes the characters of `range`, keeping track of the edit for the search.
+    fn remove_chars(&mut self, range: Range<usize>) {
+        let start = self.content.char_to_byte(range.start);
+        let end = self.content.char_to_byte(range.end);
+        self.content.remove(range);
+        self.edited(Edit {
+            start,
+            old_end: end,
+            new_end: start,
+        });
+    }
+
+    /// Replaces the whole content, keeping track of the edit for the search.
+    fn set_rope(&mut self, content: Rope) {
+        self.edited(Edit {
+            start: 0,
+            old_end: self.content.len_bytes(),
+            new_end: content.len_bytes(),
+        });
+        self.content = content;
+    }
+
+    fn edited(&mut self, edit: Edit) {
+        self.edit = Some(match self.edit {
+            Some(previous) => previous.then(edit),
+            None => edit,
+        });
+    }
+
+    /// Discards the cached highlighting and wrapping from the edited `line` onwards.
+    fn invalidate_highlighting(&mut self, line: usize) {
+        self.highlighter.get_mut().unwrap().invalidate(line);
+        self.wrap.get_mut().unwrap().invalidate(line);
+    }
+
+    /// Invalidates the caches after an edit in `line`, the content had `lines` lines before.
+    ///
+    /// If the edit didn't add or remove lines, only `line` has to be wrapped again.
+    fn invalidate_edit(&mut self, line: usize, lines: usize) {
+        if self.content.len_lines() == lines {
+            self.highlighter.get_mut().unwrap().invalidate(line);
+            self.wrap.get_mut().unwrap().invalidate_line(line);
+        } else {
+            self.invalidate_highlighting(line);
+        }
+    }
+
+    /// Amount of rows on screen, wrapped lines take up multiple ones
+    fn visual_rows(&self) -> usize {
+        if self.soft_wrap {
+            self.wrap.lock().unwrap().rows(&self.content)
+        } else {
+            self.content.len_lines()
+        }
+    }
+
+    /// The terminal column and row of `char_offset`, counting the rows of wrapped lines
+    fn visual_position(&self, char_offset: usize) -> Vec2 {
+        if self.soft_wrap {
+            let (row, column) = self
+                .wrap
+                .lock()
+                .unwrap()
+                .position(&self.content, char_offset);
+            Vec2::new(column, row)
+        } else {
+            let cursor = self.cursor_at(char_offset);
+            Vec2::new(cursor.column, cursor.row)
+        }
+    }
+
+    /// The character offset at the terminal `column` of the visual `row`, clamped to the row
+    fn visual_offset(&self, row: usize, column: usize) -> usize {
+        if self.soft_wrap {
+            self.wrap
+                .lock()
+                .unwrap()
+                .offset_at(&self.content, row, column)
+        } else {
+            self.offset_at_column(row, column)
+        }
+    }
+
+    /// The line shown in the visual `row`, the character range of the row and its indentation
+    ///
+    /// The range of the last row of a line includes the line break.
+    fn visual_row(&self, row: usize) -> (usize, Range<usize>, usize) {
+        if !self.soft_wrap {
+            return (row, self.lines_range(row, row), 0);
+        }
+        let mut wrap = self.wrap.lock().unwrap();
+        let (line, line_row) = wrap.line_at(&self.content, row);
+        let wrapped = wrap.line(&self.content, line);
+        let range = self.lines_range(line, line);
+        let start = range.start + wrapped.starts[line_row];
+        let end = match wrapped.starts.get(line_row + 1) {
+            Some(next) => range.start + next,
+            None => range.end,
+        };
+        (line, start..end, wrapped.row_indent(line_row))
+    }
+
+    /// Width of the line numbers in front of the lines, including the space after them
+    fn gutter_width(&self) -> usize {
+        if self.line_numbers {
+            self.content.len_lines().to_string().len() + 1
+        } else {
+            0
+        }
     }
 
     /// Calculates the max content width. You can add an `edited_line` to improve performance for large content greatly.
     fn compute_max_content_width(&mut self, edited_line: Option<usize>) {
-        let line_count = self.content.len_lines();
-        let line_number_width = line_count.to_string().len();
+        let gutter_width = self.gutter_width();
 
         // Helper that adds the line-number column width to a raw line width:
-        let compute_width = |raw_width| raw_width + line_number_width + 1;
+        let compute_width = |raw_width| raw_width + gutter_width;
+
+        // Tabs are counted as wide as possible, instead of measuring each line exactly.
+        let tab_width = self.tab_width;
+        let measure = |line: RopeSlice| {
+            line.len_chars() + line.chars().filter(|&c| c == '\t').count() * (tab_width - 1)
+        };
 
         /// Helper that fully scans all lines to find the max line width and its index:
-        fn full_scan(content: &Rope) -> (usize, usize) {
+        fn full_scan(content: &Rope, measure: impl Fn(RopeSlice) -> usize) -> (usize, usize) {
             content
                 .lines()
                 .enumerate()
                 .fold((0, 0), |(max_width, max_idx), (i, line)| {
-                    let width = line.len_chars();
+                    let width = measure(line);
                     if width > max_width {
                         (width, i)
                     } else {
@@ -390,31 +1017,81 @@ impl EditArea {
 
         match edited_line {
             None => {
-                let (max_line_width, max_index) = full_scan(&self.content);
+                let (max_measure, max_index) = full_scan(&self.content, measure);
                 self.max_line_index = max_index;
-                self.max_content_width = compute_width(max_line_width);
+                self.max_content_width = compute_width(max_measure);
             }
             Some(line_idx) => {
-                let new_line_width = self.content.line(line_idx).len_chars();
-                let old_max_line_width =
-                    self.max_content_width.saturating_sub(line_number_width + 1);
+                let new_measure = measure(self.content.line(line_idx));
+                let old_max_measure = self.max_content_width.saturating_sub(gutter_width);
 
                 if line_idx == self.max_line_index {
-                    if new_line_width >= old_max_line_width {
-                        self.max_content_width = compute_width(new_line_width);
+                    if new_measure >= old_max_measure {
+                        self.max_content_width = compute_width(new_measure);
                     } else {
-                        let (max_line_width, max_index) = full_scan(&self.content);
+                        let (max_measure, max_index) = full_scan(&self.content, measure);
                         self.max_line_index = max_index;
-                        self.max_content_width = compute_width(max_line_width);
+                        self.max_content_width = compute_width(max_measure);
                     }
-                } else if new_line_width > old_max_line_width {
+                } else if new_measure > old_max_measure {
                     self.max_line_index = line_idx;
-                    self.max_content_width = compute_width(new_line_width);
+                    self.max_content_width = compute_width(new_measure);
                 }
             }
         }
     }
 
+    /// The current state of the buffer for the history.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            content: self.content.clone(),
+            cursor: self.cursor,
+            scroll_offset: self.scroll(),
+        }
+    }
+
+    /// Records the current state before an edit of `kind` is applied.
+    fn record(&mut self, kind: EditKind) {
+        // Only typing and deleting keep track of the automatically closed brackets.
+        if kind == EditKind::Other {
+            self.auto_closed.clear();
+        }
+        let snapshot = self.snapshot();
+        self.buffer.lock().unwrap().history.record(kind, snapshot);
+    }
+
+    /// Restores the content, cursor and scroll offset of `snapshot`.
+    fn restore(&mut self, snapshot: Snapshot) -> Callback {
+        self.set_rope(snapshot.content);
+        self.auto_closed.clear();
+        self.selection = None;
+        self.cursors.clear();
+        self.invalidate_highlighting(0);
+        self.compute_max_content_width(None);
+        self.set_cursor(snapshot.cursor);
+        self.set_scroll(snapshot.scroll_offset);
+
+        self.on_edit_callback().unwrap_or(Callback::dummy())
+    }
+
+    /// Reverts the last edit (group).
+    fn undo(&mut self) -> Callback {
+        let snapshot = self.buffer.lock().unwrap().history.undo(self.snapshot());
+        match snapshot {
+            Some(snapshot) => self.restore(snapshot),
+            None => Callback::dummy(),
+        }
+    }
+
+    /// Reapplies the last reverted edit (group).
+    fn redo(&mut self) -> Callback {
+        let snapshot = self.buffer.lock().unwrap().history.redo(self.snapshot());
+        match snapshot {
+            Some(snapshot) => self.restore(snapshot),
+            None => Callback::dummy(),
+        }
+    }
+
     fn page_up(&mut self) -> Callback {
         for _ in 0..5 {
             self.move_up();
@@ -431,44 +1108,49 @@ impl EditArea {
         self.on_interact_callback().unwrap_or(Callback::dummy())
     }
 
+    /// Scrolls down or up by half of the visible rows, moving the cursor along.
+    fn half_page(&mut self, down: bool) -> Callback {
+        let viewport = self.scroll_core.content_viewport();
+        let rows = max(viewport.height() / 2, 1);
+        let current_row = self.visual_position(self.cursor.char_offset).y;
+        let mut scroll = viewport.top_left();
+        let row = if down {
+            scroll.y += rows;
+            min(current_row + rows, self.visual_rows() - 1)
+        } else {
+            scroll.y = scroll.y.saturating_sub(rows);
+            current_row.saturating_sub(rows)
+        };
+        self.set_scroll(scroll);
+        self.set_cursor(self.vertical_cursor(row))
+    }
+
     fn move_up(&mut self) -> Callback {
-        let current_row = self.selected_row();
+        let current_row = self.visual_position(self.cursor.char_offset).y;
         if current_row == 0 {
             return Callback::dummy();
         }
-
-        let prev_line_start = self.content.line_to_char(current_row - 1);
-        let prev_line_end = self.content.line_to_char(current_row);
-        let prev_line_len = prev_line_end - prev_line_start - 1;
-
-        let new_col = min(self.cursor.column, prev_line_len);
-        let new_char_offset = prev_line_start + new_col;
-        let new_byte_offset = self.content.char_to_byte(new_char_offset);
-
-        self.set_offset(new_byte_offset)
+        self.set_cursor(self.vertical_cursor(current_row - 1))
     }
 
-    // Broken when going to the last linesÏ
     fn move_down(&mut self) -> Callback {
-        let current_row = self.selected_row();
-        if current_row + 1 >= self.content.len_lines() {
+        let current_row = self.visual_position(self.cursor.char_offset).y;
+        if current_row + 1 >= self.visual_rows() {
             return Callback::dummy();
         }
+        self.set_cursor(self.vertical_cursor(current_row + 1))
+    }
 
-        let next_line_start = self.content.line_to_char(current_row + 1);
-
-        let next_line_end = if current_row + 2 < self.content.len_lines() {
-            self.content.line_to_char(current_row + 2)
-        } else {
-            self.content.len_chars()
-        };
-
-        let next_line_len = (next_line_end - next_line_start).saturating_sub(1);
-        let new_col = min(self.cursor.column, next_line_len);
-        let new_char_offset = next_line_start + new_col;
-        let new_byte_offset = self.content.char_to_byte(new_char_offset);
-
-        self.set_offset(new_byte_offset)
+    /// The cursor moved to the visual `row`, as close to its sticky column as the row allows.
+    fn vertical_cursor(&self, row: usize) -> Cursor {
+        let column = self
+            .cursor
+            .sticky_column
+            .unwrap_or_else(|| self.visual_position(self.cursor.char_offset).x);
+        Cursor {
+            sticky_column: Some(column),
+            ..self.cursor_at(self.visual_offset(row, column))
+        }
     }
 
     /// Moves the cursor to the left.
@@ -476,9 +1158,7 @@ impl EditArea {
         if self.cursor.char_offset == 0 {
             return Callback::dummy();
         }
-        let new_char_offset = self.cursor.char_offset - 1;
-        let new_byte_offset = self.content.char_to_byte(new_char_offset);
-        self.set_curser_from_byte_offset(new_byte_offset)
+        self.set_cursor_from_char_offset(self.prev_grapheme(self.cursor.char_offset))
     }
 
     /// Moves the cursor to the right.
@@ -486,49 +1166,138 @@ impl EditArea {
         if self.cursor.char_offset >= self.content.len_chars() {
             return Callback::dummy();
         }
-        let new_char_offset = self.cursor.char_offset + 1;
-        let new_byte_offset = self.content.char_to_byte(new_char_offset);
-        self.set_curser_from_byte_offset(new_byte_offset)
+        self.set_cursor_from_char_offset(self.next_grapheme(self.cursor.char_offset))
     }
 
     /// Moves by the mouse position and scroll offset.
     fn move_mouse(&mut self, position: XY<usize>, offset: XY<usize>) -> Callback {
-        let content_lines = self.content.len_lines();
-        if content_lines != 0 && position.fits_in_rect(offset, self.scroll_core.inner_size()) {
-            if let Some(position) = position.checked_sub(offset) {
-                let y = min(position.y, content_lines - 1);
-                let x = position
-                    .x
-                    .saturating_sub(content_lines.to_string().len() + 1);
-
-                let row_start = self.content.line_to_char(y);
-                let row_end = if y + 1 < content_lines {
-                    self.content.line_to_char(y + 1).saturating_sub(1)
-                } else {
-                    self.content.len_chars()
-                };
+        match self.mouse_offset(position, offset) {
+            Some(char_offset) => self.set_cursor_from_char_offset(char_offset),
+            None => Callback::dummy(),
+        }
+    }
 
-                let content = self.content.slice(row_start..row_end).to_string();
-                let prefix_length = grapheme_prefix_length(&content, x);
+    /// The character offset at the mouse position, `None` if it's outside of the content.
+    fn mouse_offset(&self, position: XY<usize>, offset: XY<usize>) -> Option<usize> {
+        let rows = self.visual_rows();
+        if rows == 0 || !position.fits_in_rect(offset, self.scroll_core.inner_size()) {
+            return None;
+        }
+        let position = position.checked_sub(offset)?;
+        let y = min(position.y, rows - 1);
+        let x = position.x.saturating_sub(self.gutter_width());
+        Some(self.visual_offset(y, x))
+    }
 
-                return self.set_cursor_from_char_offset(row_start + prefix_length);
+    /// Returns the selected character range, `None` if nothing is selected.
+    ///
+    /// The visual modes also select the character at the cursor or the whole lines.
+    fn selection_range(&self) -> Option<Range<usize>> {
+        let anchor = self.selection?;
+        let cursor = self.cursor.char_offset;
+        let (start, end) = (min(anchor, cursor), max(anchor, cursor));
+        match self.mode() {
+            Some(Mode::Visual) => {
+                let end = min(end + 1, self.content.len_chars());
+                (start < end).then_some(start..end)
             }
+            Some(Mode::VisualLine) => Some(self.lines_range(
+                self.content.char_to_line(start),
+                self.content.char_to_line(end),
+            )),
+            _ => (anchor != cursor).then_some(start..end),
         }
-        Callback::dummy()
     }
 
-    fn backspace(&mut self) -> Callback {
-        self.move_left();
-        self.delete()
+    /// Discards the selections of all cursors.
+    fn discard_selections(&mut self) {
+        self.selection = None;
+        for (_, anchor) in &mut self.cursors {
+            *anchor = None;
+        }
+    }
+
+    /// Starts a selection at the cursor if there isn't one already.
+    fn extend_selection(&mut self) {
+        if self.selection.is_none() {
+            self.selection = Some(self.cursor.char_offset);
+        }
+    }
+
+    /// Selects the whole content.
+    fn select_all(&mut self) -> Callback {
+        self.selection = Some(0);
+        self.set_cursor_from_char_offset(self.content.len_chars())
+    }
+
+    /// Removes the selection and the selected text, placing the cursor at its start.
+    ///
+    /// Returns `false` if nothing was selected.
+    fn remove_selection(&mut self) -> bool {
+        let range = self.selection_range();
+        self.selection = None;
+        if let Some(range) = range {
+            self.remove_range(range);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Removes the characters of `range`, placing the cursor at its start.
+    fn remove_range(&mut self, range: Range<usize>) {
+        self.invalidate_highlighting(self.content.char_to_line(range.start));
+        self.remove_chars(range.clone());
+        self.set_cursor_from_char_offset(range.start);
+        self.compute_max_content_width(None);
+    }
+
+    fn backspace(&mut self) -> Callback {
+        if self.selection_range().is_some() {
+            return self.delete();
+        }
+        if self.cursor.char_offset == 0 {
+            return Callback::dummy();
+        }
+        self.selection = None;
+        self.record(EditKind::Delete);
+        // Removing an opening bracket or quote also removes its automatically inserted closer.
+        let offset = self.cursor.char_offset;
+        let closer = self.auto_closed.contains(&offset)
+            && is_pair(self.content.char(offset - 1), self.content.char(offset));
+        self.move_left();
+        let callback = self.remove();
+        if closer {
+            self.remove()
+        } else {
+            callback
+        }
     }
 
-    // Broken when deleting multiline text
     fn delete(&mut self) -> Callback {
+        if self.selection_range().is_some() {
+            self.record(EditKind::Other);
+            self.remove_selection();
+            return self.on_edit_callback().unwrap_or_else(Callback::dummy);
+        }
+        self.selection = None;
+        self.record(EditKind::Delete);
+        self.remove()
+    }
+
+    /// Removes the character at the cursor.
+    // Broken when deleting multiline text
+    fn remove(&mut self) -> Callback {
         if self.cursor.char_offset >= self.content.len_chars() {
             return Callback::dummy();
         }
-        let end = self.cursor.char_offset + 1;
-        self.content.remove(self.cursor.char_offset..end);
+        let end = self.next_grapheme(self.cursor.char_offset);
+        let lines = self.content.len_lines();
+        self.remove_chars(self.cursor.char_offset..end);
+        self.invalidate_edit(self.selected_row(), lines);
+        self.auto_closed
+            .retain(|&closed| closed != self.cursor.char_offset);
+        self.shift_auto_closed(end, -((end - self.cursor.char_offset) as isize));
 
         // Recaulcuate the available width for the current edited line.
         let current_line = self.content.char_to_line(self.cursor.char_offset);
@@ -538,8 +1307,38 @@ impl EditArea {
     }
 
     fn insert(&mut self, ch: char) -> Callback {
-        let old_line = self.content.char_to_line(self.cursor.char_offset);
-        self.content.insert_char(self.cursor.char_offset, ch);
+        // Typing an automatically inserted closer steps over it.
+        let offset = self.cursor.char_offset;
+        if self.auto_closed.contains(&offset)
+            && self.content.get_char(offset) == Some(ch)
+            && self.selection_range().is_none()
+        {
+            self.auto_closed.retain(|&closed| closed != offset);
+            return self.move_right();
+        }
+        // A new line or replacing a selection always starts a new undo step.
+        if ch == '\n' || self.selection_range().is_some() {
+            self.buffer.lock().unwrap().history.seal();
+        }
+        self.record(EditKind::Insert);
+        self.remove_selection();
+        // A closing bracket at the start of a line is aligned with its opening one.
+        if let Some(&pair) = BRACKETS.iter().find(|&&(_, close)| close == ch) {
+            self.dedent_cursor(pair);
+        }
+
+        let offset = self.cursor.char_offset;
+        let closer = self.auto_closer(ch, offset);
+        let old_line = self.content.char_to_line(offset);
+        let lines = self.content.len_lines();
+        self.insert_chars(offset, ch.encode_utf8(&mut [0; 4]));
+        self.shift_auto_closed(offset, 1);
+        if let Some(closer) = closer {
+            self.insert_chars(offset + 1, closer.encode_utf8(&mut [0; 4]));
+            self.shift_auto_closed(offset + 1, 1);
+            self.auto_closed.push(offset + 1);
+        }
+        self.invalidate_edit(old_line, lines);
 
         // Then, we shift the indexes of every row after this one.
         let shift = ch.len_utf8();
@@ -547,10 +1346,8 @@ impl EditArea {
         // Update cursor
         self.set_curser_from_byte_offset(self.cursor.byte_offset + shift);
 
-        // Check if newline char, if true reset the cached column.
-        // Also compute the max_content_width with the old line index.
+        // Compute the max_content_width with the old line index for a newline char.
         if ch == '\n' {
-            self.cursor.column = 0;
             self.compute_max_content_width(Some(old_line));
         } else {
             let current_line = self.content.char_to_line(self.cursor.char_offset);
@@ -560,37 +1357,188 @@ impl EditArea {
         self.on_edit_callback().unwrap_or_else(Callback::dummy)
     }
 
-    /// Copies the line where the cursor currently is.
-    fn copy(&mut self) {
-        let row = self.row_at(self.cursor.byte_offset);
-        let line_slice = self.content.line(row);
+    /// Breaks the line at the cursor, keeping its indentation.
+    ///
+    /// After an opening bracket the new line is indented once more, its closing bracket right
+    /// at the cursor moves to a line of its own.
+    fn newline(&mut self) -> Callback {
+        self.buffer.lock().unwrap().history.seal();
+        self.record(EditKind::Insert);
+        self.remove_selection();
+
+        let offset = self.cursor.char_offset;
+        let row = self.selected_row();
+        let (line_start, _) = self.line_bounds(row);
+        let indentation = self
+            .content
+            .slice(line_start..min(self.first_non_blank(row), offset))
+            .to_string();
+        let before = self
+            .content
+            .slice(line_start..offset)
+            .to_string()
+            .trim_end()
+            .chars()
+            .last();
+        let after = (offset < self.content.len_chars()).then(|| self.content.char(offset));
+
+        let mut text = format!("\n{indentation}");
+        // the line break of the closing bracket, after the cursor
+        let mut closing = String::new();
+        if let Some(&(_, close)) = BRACKETS.iter().find(|&&(open, _)| Some(open) == before) {
+            text.push_str(&self.indent.unit());
+            if after == Some(close) {
+                closing = format!("\n{indentation}");
+            }
+        }
+
+        let text_len = text.chars().count();
+        self.invalidate_highlighting(row);
+        self.insert_chars(offset, &format!("{text}{closing}"));
+        self.shift_auto_closed(offset, (text_len + closing.chars().count()) as isize);
+        self.set_cursor_from_char_offset(offset + text_len);
+        self.compute_max_content_width(None);
+
+        self.on_edit_callback().unwrap_or_else(Callback::dummy)
+    }
+
+    /// Dedents the whitespace in front of the cursor to the indentation of the line with the
+    /// opening bracket of `pair`, if there is only whitespace and it's indented further.
+    ///
+    /// Without an opening bracket the previous non-blank line is dedented by one level.
+    fn dedent_cursor(&mut self, pair: (char, char)) {
+        let offset = self.cursor.char_offset;
+        let row = self.selected_row();
+        let (line_start, _) = self.line_bounds(row);
+        let leading = self.content.slice(line_start..offset).to_string();
+        if leading.contains(|c: char| !c.is_whitespace()) {
+            return;
+        }
+
+        let indentation = |row: usize| {
+            let (start, _) = self.line_bounds(row);
+            self.content
+                .slice(start..self.first_non_blank(row))
+                .to_string()
+        };
+        let target = match self.unmatched_bracket(offset, pair, false) {
+            Some(open) => indentation(self.content.char_to_line(open)),
+            None => {
+                let Some(previous) = (0..row)
+                    .rev()
+                    .find(|&row| self.first_non_blank(row) < self.line_bounds(row).1)
+                else {
+                    return;
+                };
+                let mut previous = indentation(previous);
+                let len = if previous.ends_with('\t') {
+                    1
+                } else {
+                    previous
+                        .chars()
+                        .rev()
+                        .take(self.indent.width(self.tab_width))
+                        .take_while(|&c| c == ' ')
+                        .count()
+                };
+                previous.truncate(previous.len() - len);
+                previous
+            }
+        };
+        if line_width(&leading, self.tab_width) <= line_width(&target, self.tab_width) {
+            return;
+        }
+
+        self.remove_chars(line_start..offset);
+        self.insert_chars(line_start, &target);
+        let len = target.chars().count();
+        self.shift_auto_closed(offset, len as isize - leading.chars().count() as isize);
+        self.set_cursor_from_char_offset(line_start + len);
+    }
+
+    /// The closer inserted automatically when typing `ch` at `offset`, if it opens a pair.
+    ///
+    /// Only brackets and quotes followed by whitespace or a closing bracket are closed, quotes
+    /// also only after whitespace or an opening bracket, e.g. not in `don't` or `&'a`.
+    fn auto_closer(&self, ch: char, offset: usize) -> Option<char> {
+        if !self.auto_pairs {
+            return None;
+        }
+        let closer = BRACKETS
+            .iter()
+            .find(|&&(open, _)| open == ch)
+            .map(|&(_, close)| close)
+            .or_else(|| QUOTES.contains(&ch).then_some(ch))?;
+        let free_after = match self.content.get_char(offset) {
+            Some(next) => next.is_whitespace() || BRACKETS.iter().any(|&(_, close)| close == next),
+            None => true,
+        };
+        let free_before = match offset.checked_sub(1).map(|i| self.content.char(i)) {
+            Some(previous) if QUOTES.contains(&ch) => {
+                previous.is_whitespace() || BRACKETS.iter().any(|&(open, _)| open == previous)
+            }
+            _ => true,
+        };
+        (free_after && free_before).then_some(closer)
+    }
 
-        let mut copied = line_slice.to_string();
-        if !copied.ends_with('\n') {
-            copied.push('\n');
+    /// Shifts the automatically closed brackets from `offset` on by `delta` characters.
+    fn shift_auto_closed(&mut self, offset: usize, delta: isize) {
+        for closed in &mut self.auto_closed {
+            if *closed >= offset {
+                *closed = closed.saturating_add_signed(delta);
+            }
         }
+    }
+
+    /// Copies the selection or the line where the cursor currently is.
+    fn copy(&mut self) {
+        let copied = if let Some(range) = self.selection_range() {
+            self.content.slice(range).to_string()
+        } else {
+            let row = self.row_at(self.cursor.byte_offset);
+            let mut copied = self.content.line(row).to_string();
+            if !copied.ends_with('\n') {
+                copied.push('\n');
+            }
+            copied
+        };
 
         crate::clipboard::set_content(copied).unwrap_or_else(|e| error!("{e}"));
     }
 
-    /// Pastes the current clipboard at the cursor position.
+    /// Pastes the current clipboard at every cursor, replacing the selections.
     fn paste(&mut self) -> Callback {
+        match crate::clipboard::get_content() {
+            Ok(text) => self.at_each_cursor(|area| area.insert_text(&text)),
+            Err(_) => Callback::dummy(),
+        }
+    }
+
+    /// Inserts `text` at the cursor position, replacing the selection.
+    fn insert_text(&mut self, text: &str) -> Callback {
+        self.record(EditKind::Other);
+        self.remove_selection();
+
         let cursor_pos = self.cursor.char_offset;
-        if let Ok(text) = crate::clipboard::get_content() {
-            self.content.insert(cursor_pos, &text);
-            self.set_cursor_from_char_offset(cursor_pos + text.chars().count());
+        self.invalidate_highlighting(self.selected_row());
+        self.insert_chars(cursor_pos, text);
+        self.set_cursor_from_char_offset(cursor_pos + text.chars().count());
 
-            let current_line = self.content.char_to_line(self.cursor.char_offset);
-            self.compute_max_content_width(Some(current_line));
+        let current_line = self.content.char_to_line(self.cursor.char_offset);
+        self.compute_max_content_width(Some(current_line));
 
-            self.on_edit_callback().unwrap_or(Callback::dummy())
-        } else {
-            Callback::dummy()
-        }
+        self.on_edit_callback().unwrap_or(Callback::dummy())
     }
 
-    /// Cuts (copies and removes) the line where the cursor currently is.
+    /// Cuts (copies and removes) the selection or the line where the cursor currently is.
     fn cut(&mut self) -> Callback {
+        if self.selection_range().is_some() {
+            self.copy();
+            return self.delete();
+        }
+        self.selection = None;
+
         let row = self.row_at(self.cursor.byte_offset);
 
         let line_slice = self.content.line(row);
@@ -601,13 +1549,15 @@ impl EditArea {
 
         crate::clipboard::set_content(line_text).unwrap_or_else(|e| error!("{e}"));
 
+        self.record(EditKind::Other);
+        self.invalidate_highlighting(row);
         let start = self.content.line_to_char(row);
         let end = if row + 1 < self.content.len_lines() {
             self.content.line_to_char(row + 1)
         } else {
             self.content.len_chars()
         };
-        self.content.remove(start..end);
+        self.remove_chars(start..end);
 
         self.set_cursor_from_char_offset(start);
 
@@ -617,78 +1567,190 @@ impl EditArea {
         self.on_edit_callback().unwrap_or(Callback::dummy())
     }
 
-    /// Implements the tabulator. If `ident` is true, insert (indent) a tab;
-    /// otherwise, remove (unindent) a tab if present.
-    fn tabulator(&mut self, ident: bool) -> Callback {
-        let row = self.row_at(self.cursor.byte_offset);
-        let line_start = self.content.line_to_char(row);
-        let line_end = if row + 1 < self.content.len_lines() {
-            self.content.line_to_char(row + 1)
-        } else {
-            self.content.len_chars()
-        };
-
-        let line_text = self.content.slice(line_start..line_end).to_string();
-        let tab_size = 4;
-        let tab_str = " ".repeat(tab_size);
-        let new_line_text = if ident {
-            format!("{}{}", tab_str, line_text)
-        } else if line_text.starts_with(&tab_str) {
-            line_text[tab_size..].to_string()
-        } else {
-            line_text.clone()
+    /// Cuts the rest of the line, or the line break at its end.
+    fn delete_to_line_end(&mut self) -> Callback {
+        self.selection = None;
+        let (_, line_end) = self.line_bounds(self.selected_row());
+        let end = match self.cursor.char_offset < line_end {
+            true => line_end,
+            false => min(line_end + 1, self.content.len_chars()),
         };
+        let range = self.cursor.char_offset..end;
+        if range.is_empty() {
+            return Callback::dummy();
+        }
+        let text = self.content.slice(range.clone()).to_string();
+        crate::clipboard::set_content(text).unwrap_or_else(|e| error!("{e}"));
 
-        if new_line_text != line_text {
-            self.content.remove(line_start..line_end);
-            self.content.insert(line_start, &new_line_text);
+        self.record(EditKind::Other);
+        self.remove_range(range);
+        self.on_edit_callback().unwrap_or_else(Callback::dummy)
+    }
 
-            let current_offset = self.cursor.char_offset - line_start;
-            let new_offset = if ident {
-                current_offset + tab_size
+    /// Implements the tabulator. If `ident` is true, insert (indent) a level of the buffer's
+    /// indentation; otherwise, remove (unindent) a tab or up to a level of spaces if present.
+    ///
+    /// Applies to every line touched by the selections, or the lines of the cursors.
+    fn tabulator(&mut self, ident: bool) -> Callback {
+        let primary = self
+            .selection_range()
+            .unwrap_or(self.cursor.char_offset..self.cursor.char_offset);
+        let mut rows = Vec::new();
+        for range in iter::once(primary).chain(self.cursors.iter().map(|&c| selected(c))) {
+            let first_row = self.content.char_to_line(range.start);
+            let last_row = self.content.char_to_line(range.end);
+            // A selection ending at the start of a line doesn't touch this line.
+            if last_row > first_row && self.content.line_to_char(last_row) == range.end {
+                rows.extend(first_row..last_row);
             } else {
-                current_offset.saturating_sub(tab_size)
+                rows.extend(first_row..=last_row);
+            }
+        }
+        rows.sort_unstable();
+        rows.dedup();
+
+        // The rows with the amount of characters inserted or removed at their start.
+        let unit = self.indent.unit();
+        let changed_rows = rows
+            .into_iter()
+            .filter_map(|row| {
+                if ident {
+                    return Some((row, unit.chars().count()));
+                }
+                // A tab or the spaces of up to one level are removed.
+                let mut chars = self.content.line(row).chars();
+                let len = match chars.next() {
+                    Some('\t') => 1,
+                    Some(' ') => {
+                        1 + chars
+                            .take(self.indent.width(self.tab_width) - 1)
+                            .take_while(|&c| c == ' ')
+                            .count()
+                    }
+                    _ => 0,
+                };
+                (len > 0).then_some((row, len))
+            })
+            .collect::<Vec<_>>();
+
+        if let (Some(&(first_row, _)), Some(&(last_row, _))) =
+            (changed_rows.first(), changed_rows.last())
+        {
+            self.record(EditKind::Other);
+
+            // Keep the cursors and the selection anchors at their position inside the line.
+            let position = |offset: usize| {
+                let row = self.content.char_to_line(offset);
+                (row, offset - self.content.line_to_char(row))
             };
-            self.set_cursor_from_char_offset(line_start + new_offset);
+            let positions = self
+                .all_cursors()
+                .map(|(cursor, anchor)| (position(cursor.char_offset), anchor.map(position)))
+                .collect::<Vec<_>>();
+
+            self.invalidate_highlighting(first_row);
+            for &(row, len) in &changed_rows {
+                let line_start = self.content.line_to_char(row);
+                if ident {
+                    self.insert_chars(line_start, &unit);
+                } else {
+                    self.remove_chars(line_start..line_start + len);
+                }
+            }
 
-            let current_line = self.content.char_to_line(self.cursor.char_offset);
-            self.compute_max_content_width(Some(current_line));
+            let offset = |(row, col): (usize, usize)| {
+                let changed = changed_rows
+                    .binary_search_by_key(&row, |&(row, _)| row)
+                    .map(|i| changed_rows[i].1);
+                let col = match changed {
+                    Ok(len) if ident => col + len,
+                    Ok(len) => col.saturating_sub(len),
+                    Err(_) => col,
+                };
+                self.content.line_to_char(row) + col
+            };
+            let mut cursors = positions
+                .into_iter()
+                .map(|(cursor, anchor)| {
+                    let cursor = self.cursor_at(offset(cursor));
+                    (cursor, anchor.map(offset))
+                })
+                .collect::<Vec<_>>();
+            let (cursor, anchor) = cursors.remove(0);
+            self.cursors = cursors;
+            self.selection = anchor;
+            self.set_cursor(cursor);
+
+            if first_row == last_row {
+                self.compute_max_content_width(Some(first_row));
+            } else {
+                self.compute_max_content_width(None);
+            }
         }
 
         self.on_edit_callback().unwrap_or(Callback::dummy())
     }
 
-    /// Moves the line containing the cursor up or down.
+    /// Moves the lines containing a cursor up or down.
     fn move_line(&mut self, direction: Key) -> Callback {
         let num_lines = self.content.len_lines();
-        let current_line = self.row_at(self.cursor.byte_offset);
-
-        if (current_line == 0 && direction == Key::Up)
-            || (current_line == num_lines - 1 && direction == Key::Down)
+        let mut rows = self
+            .all_cursors()
+            .map(|(cursor, _)| self.content.char_to_line(cursor.char_offset))
+            .collect::<Vec<_>>();
+        rows.sort_unstable();
+        rows.dedup();
+
+        let (Some(&first_row), Some(&last_row)) = (rows.first(), rows.last()) else {
+            return Callback::dummy();
+        };
+        if (first_row == 0 && direction == Key::Up)
+            || (last_row == num_lines - 1 && direction == Key::Down)
         {
             return Callback::dummy();
         }
-
-        let target_line = if direction == Key::Up {
-            current_line - 1
-        } else {
-            current_line + 1
+        let target = |row: usize| match direction {
+            Key::Up => row - 1,
+            _ => row + 1,
         };
 
-        let current_start = self.content.line_to_char(current_line);
-        let cursor_in_line = self.cursor.char_offset - current_start;
-
-        swap_lines(&mut self.content, current_line, target_line);
+        // Keep the cursors at their position inside the line.
+        let positions = self
+            .all_cursors()
+            .map(|(cursor, _)| {
+                let row = self.content.char_to_line(cursor.char_offset);
+                (row, cursor.char_offset - self.content.line_to_char(row))
+            })
+            .collect::<Vec<_>>();
+
+        self.selection = None;
+        self.record(EditKind::Other);
+        self.invalidate_highlighting(min(first_row, target(first_row)));
+        // Neighbouring lines move as a block, starting with the line in front.
+        if direction == Key::Down {
+            rows.reverse();
+        }
+        for row in rows {
+            swap_lines(&mut self.content, row, target(row));
+        }
 
-        let new_line_start = self.content.line_to_char(target_line);
-        let new_cursor_pos = new_line_start + cursor_in_line;
-        self.set_cursor_from_char_offset(new_cursor_pos);
+        let mut cursors = positions
+            .into_iter()
+            .map(|(row, col)| {
+                let line_start = self.content.line_to_char(target(row));
+                (self.cursor_at(line_start + col), None)
+            })
+            .collect::<Vec<_>>();
+        let (cursor, _) = cursors.remove(0);
+        self.cursors = cursors;
+        self.set_cursor(cursor);
 
         self.on_edit_callback().unwrap_or(Callback::dummy())
     }
 
     /// Moves the cursor to the start or end of the current line.
-    /// For left, moves to the beginning; for right, to the last character (before newline).
+    /// For left, moves to the first non-blank character or from there to the beginning;
+    /// for right, to the last character (before newline).
     fn move_cursor_end(&mut self, direction: Key) -> Callback {
         let row = self.row_at(self.cursor.byte_offset);
         let line_start = self.content.line_to_char(row);
@@ -698,13 +1760,729 @@ impl EditArea {
         } else {
             self.content.len_chars()
         };
+        let first_non_blank = self.first_non_blank(row);
         match direction {
+            Key::Left if self.cursor.char_offset != first_non_blank => {
+                self.set_cursor_from_char_offset(first_non_blank)
+            }
             Key::Left => self.set_cursor_from_char_offset(line_start),
             Key::Right => self.set_cursor_from_char_offset(line_end),
             _ => Callback::dummy(),
         }
     }
 
+    /// Moves the cursor to the start of the previous or the end of the next word.
+    fn move_word(&mut self, direction: Key) -> Callback {
+        let offset = self.cursor.char_offset;
+        match direction {
+            Key::Left => self.set_cursor_from_char_offset(self.prev_word_boundary(offset)),
+            _ => self.set_cursor_from_char_offset(self.next_word_boundary(offset)),
+        }
+    }
+
+    /// Deletes the selection, or up to the start of the previous or the end of the next word.
+    fn delete_word(&mut self, direction: Key) -> Callback {
+        if self.selection_range().is_some() {
+            return self.delete();
+        }
+        let offset = self.cursor.char_offset;
+        let range = match direction {
+            Key::Left => self.prev_word_boundary(offset)..offset,
+            _ => offset..self.next_word_boundary(offset),
+        };
+        if range.is_empty() {
+            return Callback::dummy();
+        }
+        self.record(EditKind::Other);
+        self.remove_range(range);
+        self.on_edit_callback().unwrap_or_else(Callback::dummy)
+    }
+
+    /// Moves the cursor to the blank line above or below its paragraph, or to the start or
+    /// end of the content if there is none.
+    fn move_paragraph(&mut self, direction: Key) -> Callback {
+        let last_row = self.content.len_lines().saturating_sub(1);
+        let mut row = self.selected_row();
+        let target = match direction {
+            Key::Up => {
+                while row > 0 && self.is_blank(row) {
+                    row -= 1;
+                }
+                while row > 0 && !self.is_blank(row) {
+                    row -= 1;
+                }
+                self.content.line_to_char(row)
+            }
+            _ => {
+                while row < last_row && self.is_blank(row) {
+                    row += 1;
+                }
+                while row < last_row && !self.is_blank(row) {
+                    row += 1;
+                }
+                match self.is_blank(row) {
+                    true => self.content.line_to_char(row),
+                    false => self.line_bounds(row).1,
+                }
+            }
+        };
+        self.set_cursor_from_char_offset(target)
+    }
+
+    /// The primary and the further cursors with their selection anchors.
+    fn all_cursors(&self) -> impl Iterator<Item = (Cursor, Option<usize>)> + '_ {
+        iter::once((self.cursor, self.selection)).chain(self.cursors.iter().copied())
+    }
+
+    /// Runs `edit` at every cursor, each one being the primary cursor while it runs.
+    ///
+    /// The cursors are handled from the last to the first one, so an edit only shifts the
+    /// cursors handled before it. Cursors ending up at the same position are merged.
+    fn at_each_cursor(&mut self, edit: impl Fn(&mut Self) -> Callback) -> Callback {
+        if self.cursors.is_empty() {
+            return edit(self);
+        }
+        let content = self.content.clone();
+        let mut history = None;
+
+        let mut cursors = mem::take(&mut self.cursors);
+        cursors.push((self.cursor, self.selection));
+        let primary = cursors.len() - 1;
+        let mut order = (0..cursors.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| Reverse(selected(cursors[i]).start));
+
+        for (n, &i) in order.iter().enumerate() {
+            (self.cursor, self.selection) = cursors[i];
+            let len = self.content.len_chars();
+            edit(self);
+            cursors[i] = (self.cursor, self.selection);
+            // The edits at the other cursors are undone together with the first one.
+            if n == 0 {
+                history = Some(mem::take(&mut self.buffer.lock().unwrap().history));
+            }
+
+            let delta = self.content.len_chars() as isize - len as isize;
+            if delta != 0 {
+                let len = self.content.len_chars();
+                let shift = |offset: usize| min(offset.saturating_add_signed(delta), len);
+                for &j in &order[..n] {
+                    let (cursor, anchor) = cursors[j];
+                    let cursor = self.cursor_at(shift(cursor.char_offset));
+                    cursors[j] = (cursor, anchor.map(shift));
+                }
+            }
+        }
+        if let Some(history) = history {
+            self.buffer.lock().unwrap().history = history;
+        }
+
+        let (cursor, selection) = cursors.swap_remove(primary);
+        cursors.retain(|(other, _)| other.char_offset != cursor.char_offset);
+        cursors.sort_by_key(|(cursor, _)| cursor.char_offset);
+        cursors.dedup_by_key(|(cursor, _)| cursor.char_offset);
+        self.cursors = cursors;
+        self.selection = selection;
+        self.set_cursor(cursor);
+
+        if self.content == content {
+            self.on_interact_callback().unwrap_or_else(Callback::dummy)
+        } else {
+            self.on_edit_callback().unwrap_or_else(Callback::dummy)
+        }
+    }
+
+    /// Adds `cursor` selecting from `anchor` as the primary cursor, keeping the current one.
+    fn add_cursor(&mut self, cursor: Cursor, anchor: Option<usize>) -> Callback {
+        let previous = (self.cursor, self.selection);
+        self.cursors
+            .retain(|(other, _)| other.char_offset != cursor.char_offset);
+        if previous.0.char_offset != cursor.char_offset {
+            self.cursors.push(previous);
+        }
+        self.selection = anchor;
+        self.set_cursor(cursor)
+    }
+
+    /// Adds a cursor in the line above or below the primary one, keeping its column.
+    fn add_cursor_vertically(&mut self, direction: Key) -> Callback {
+        let row = self.visual_position(self.cursor.char_offset).y;
+        let target = match direction {
+            Key::Up if row > 0 => row - 1,
+            Key::Down if row + 1 < self.visual_rows() => row + 1,
+            _ => return Callback::dummy(),
+        };
+        self.add_cursor(self.vertical_cursor(target), None)
+    }
+
+    /// Adds a cursor at the mouse position, or removes the cursor which is already there.
+    fn toggle_cursor(&mut self, position: XY<usize>, offset: XY<usize>) -> Callback {
+        let Some(char_offset) = self.mouse_offset(position, offset) else {
+            return Callback::dummy();
+        };
+        if let Some(i) = self
+            .cursors
+            .iter()
+            .position(|(cursor, _)| cursor.char_offset == char_offset)
+        {
+            self.cursors.remove(i);
+            return self.on_interact_callback().unwrap_or_else(Callback::dummy);
+        }
+        if char_offset == self.cursor.char_offset {
+            // The last added cursor becomes the primary one.
+            return match self.cursors.pop() {
+                Some((cursor, anchor)) => {
+                    self.selection = anchor;
+                    self.set_cursor(cursor)
+                }
+                None => Callback::dummy(),
+            };
+        }
+        self.add_cursor(self.cursor_at(char_offset), None)
+    }
+
+    /// Selects the word at the cursor, or the next occurrence of the selection with an
+    /// additional cursor.
+    fn select_next(&mut self) -> Callback {
+        let Some(range) = self.selection_range() else {
+            let offset = self.cursor.char_offset;
+            let mut start = match self.class_at(offset) {
+                1 => offset,
+                _ if offset > 0 && self.class_at(offset - 1) == 1 => offset - 1,
+                _ => return Callback::dummy(),
+            };
+            while start > 0 && self.class_at(start - 1) == 1 {
+                start -= 1;
+            }
+            let mut end = start;
+            while self.class_at(end) == 1 {
+                end += 1;
+            }
+            self.selection = Some(start);
+            return self.set_cursor_from_char_offset(end);
+        };
+
+        let needle = self.content.slice(range.clone()).to_string();
+        let text = self.content.to_string();
+        let from = self.content.char_to_byte(range.end);
+        // Search from the selection on, continuing at the start of the content.
+        let next = text[from..]
+            .match_indices(&needle)
+            .map(|(i, _)| from + i)
+            .chain(text.match_indices(&needle).map(|(i, _)| i))
+            .map(|i| self.content.byte_to_char(i))
+            .find(|&start| {
+                self.all_cursors()
+                    .all(|cursor| selected(cursor).start != start)
+            });
+        match next {
+            Some(start) => {
+                let end = start + needle.chars().count();
+                self.add_cursor(self.cursor_at(end), Some(start))
+            }
+            None => Callback::dummy(),
+        }
+    }
+
+    /// The character offsets of the start and the end (before the line break) of `row`.
+    fn line_bounds(&self, row: usize) -> (usize, usize) {
+        let start = self.content.line_to_char(row);
+        let end = if row + 1 < self.content.len_lines() {
+            self.content.line_to_char(row + 1) - 1
+        } else {
+            self.content.len_chars()
+        };
+        (start, end)
+    }
+
+    /// The characters of the lines `first` to `last`, including the last line break.
+    fn lines_range(&self, first: usize, last: usize) -> Range<usize> {
+        let end = if last + 1 < self.content.len_lines() {
+            self.content.line_to_char(last + 1)
+        } else {
+            self.content.len_chars()
+        };
+        self.content.line_to_char(first)..end
+    }
+
+    /// The offset of the first non-blank character of `row`.
+    fn first_non_blank(&self, row: usize) -> usize {
+        let (start, end) = self.line_bounds(row);
+        (start..end)
+            .find(|&i| !self.content.char(i).is_whitespace())
+            .unwrap_or(end)
+    }
+
+    /// The offsets of the bracket at the cursor, or otherwise right in front of it, and of its
+    /// matching bracket.
+    fn matching_brackets(&self) -> Option<(usize, usize)> {
+        let offset = self.cursor.char_offset;
+        iter::once(offset)
+            .chain(offset.checked_sub(1))
+            .find_map(|bracket| Some((bracket, self.matching_bracket(bracket)?)))
+    }
+
+    /// The offset of the bracket matching the one at `offset`.
+    ///
+    /// Brackets in strings and comments are skipped.
+    fn matching_bracket(&self, offset: usize) -> Option<usize> {
+        let c = self.content.get_char(offset)?;
+        let (&pair, forward) = BRACKETS.iter().find_map(|pair| match c {
+            c if c == pair.0 => Some((pair, true)),
+            c if c == pair.1 => Some((pair, false)),
+            _ => None,
+        })?;
+        self.unmatched_bracket(offset, pair, forward)
+    }
+
+    /// The offset of the first bracket of `pair` after or before `offset` which isn't matched
+    /// by one in between, the closing one if searching `forward`.
+    ///
+    /// Skips brackets like `matching_bracket`, `None` if the bracket at `offset` is skipped.
+    fn unmatched_bracket(&self, offset: usize, pair: (char, char), forward: bool) -> Option<usize> {
+        let (open, close) = pair;
+        let (same, target) = if forward {
+            (open, close)
+        } else {
+            (close, open)
+        };
+
+        let mut highlighter = self.highlighter.lock().unwrap();
+        let row = self.content.char_to_line(offset);
+        let rows: Box<dyn Iterator<Item = usize>> = if forward {
+            Box::new(row..min(row + MATCH_LINES, self.content.len_lines()))
+        } else {
+            Box::new((row.saturating_sub(MATCH_LINES)..=row).rev())
+        };
+        let mut depth = 0;
+        for row in rows {
+            let literals = highlighter.literals(&self.content, row, &self.syntax, &self.theme);
+            let line = self.content.line(row).to_string();
+            let line_start = self.content.line_to_char(row);
+            let mut brackets = Vec::new();
+            for (char_offset, (byte, c)) in (line_start..).zip(line.char_indices()) {
+                let skipped = literals.iter().any(|literal| literal.contains(&byte));
+                if !skipped && (c == open || c == close) {
+                    brackets.push((char_offset, c));
+                } else if skipped && char_offset == offset && (c == open || c == close) {
+                    return None;
+                }
+            }
+            if !forward {
+                brackets.reverse();
+            }
+
+            let brackets =
+                brackets
+                    .into_iter()
+                    .filter(|&(i, _)| if forward { i > offset } else { i < offset });
+            for (i, c) in brackets {
+                if c == same {
+                    depth += 1;
+                } else if c == target && depth == 0 {
+                    return Some(i);
+                } else if c == target {
+                    depth -= 1;
+                }
+            }
+        }
+        None
+    }
+
+    /// Moves the cursor to the bracket matching the one at or in front of it.
+    fn jump_to_bracket(&mut self) -> Callback {
+        match self.matching_brackets() {
+            Some((_, target)) => self.set_cursor_from_char_offset(target),
+            None => Callback::dummy(),
+        }
+    }
+
+    /// Whether `row` contains only whitespace.
+    fn is_blank(&self, row: usize) -> bool {
+        self.first_non_blank(row) == self.line_bounds(row).1
+    }
+
+    /// The end of the next word from `offset` on, by the Unicode word boundaries.
+    ///
+    /// At the end of a line it's the start of the next line.
+    fn next_word_boundary(&self, offset: usize) -> usize {
+        let (start, end) = self.line_bounds(self.content.char_to_line(offset));
+        if offset >= end {
+            return min(end + 1, self.content.len_chars());
+        }
+        let line = self.content.slice(start..end).to_string();
+        let mut word_start = start;
+        for word in line.split_word_bounds() {
+            let word_end = word_start + word.chars().count();
+            if word_end > offset && !word.trim().is_empty() {
+                return word_end;
+            }
+            word_start = word_end;
+        }
+        end
+    }
+
+    /// The start of the previous word before `offset`, by the Unicode word boundaries.
+    ///
+    /// At the start of a line it's the end of the previous line.
+    fn prev_word_boundary(&self, offset: usize) -> usize {
+        let (start, end) = self.line_bounds(self.content.char_to_line(offset));
+        if offset <= start {
+            return start.saturating_sub(1);
+        }
+        let line = self.content.slice(start..end).to_string();
+        let mut word_start = start;
+        let mut previous = start;
+        for word in line.split_word_bounds() {
+            if word_start >= offset {
+                break;
+            }
+            if !word.trim().is_empty() {
+                previous = word_start;
+            }
+            word_start += word.chars().count();
+        }
+        previous
+    }
+
+    /// The class of the character at `offset`, whitespace past the end.
+    fn class_at(&self, offset: usize) -> u8 {
+        if offset < self.content.len_chars() {
+            char_class(self.content.char(offset))
+        } else {
+            0
+        }
+    }
+
+    /// The start of the next word after `offset`.
+    fn next_word_start(&self, mut offset: usize) -> usize {
+        let len = self.content.len_chars();
+        let class = self.class_at(offset);
+        while offset < len && class != 0 && self.class_at(offset) == class {
+            offset += 1;
+        }
+        while offset < len && self.class_at(offset) == 0 {
+            offset += 1;
+        }
+        offset
+    }
+
+    /// The last character of the word ending after `offset`.
+    fn next_word_end(&self, offset: usize) -> usize {
+        let len = self.content.len_chars();
+        let mut offset = offset + 1;
+        while offset < len && self.class_at(offset) == 0 {
+            offset += 1;
+        }
+        let class = self.class_at(offset);
+        while offset + 1 < len && self.class_at(offset + 1) == class {
+            offset += 1;
+        }
+        min(offset, len.saturating_sub(1))
+    }
+
+    /// The start of the word before `offset`.
+    fn prev_word_start(&self, mut offset: usize) -> usize {
+        while offset > 0 && self.class_at(offset - 1) == 0 {
+            offset -= 1;
+        }
+        let class = self.class_at(offset.saturating_sub(1));
+        while offset > 0 && self.class_at(offset - 1) == class {
+            offset -= 1;
+        }
+        offset
+    }
+
+    /// The character offset `motion` moves the cursor to, repeated `count` times.
+    fn motion_target(&self, motion: Motion, count: usize) -> usize {
+        let offset = self.cursor.char_offset;
+        let row = self.selected_row();
+        let (line_start, line_end) = self.line_bounds(row);
+        let last_row = self.content.len_lines().saturating_sub(1);
+        let repeat = |step: &dyn Fn(usize) -> usize| (0..count).fold(offset, |o, _| step(o));
+        match motion {
+            Motion::Left => max(repeat(&|o| self.prev_grapheme(o)), line_start),
+            Motion::Right => min(repeat(&|o| self.next_grapheme(o)), line_end),
+            Motion::Up => self.first_non_blank(row.saturating_sub(count)),
+            Motion::Down => self.first_non_blank(min(row + count, last_row)),
+            Motion::WordStart => repeat(&|o| self.next_word_start(o)),
+            Motion::WordEnd => repeat(&|o| self.next_word_end(o)),
+            Motion::WordBack => repeat(&|o| self.prev_word_start(o)),
+            Motion::LineStart => line_start,
+            Motion::FirstNonBlank => self.first_non_blank(row),
+            Motion::LineEnd => line_end,
+            Motion::FileStart => self.first_non_blank(min(count.saturating_sub(1), last_row)),
+            Motion::FileEnd if count == 0 => self.first_non_blank(last_row),
+            Motion::FileEnd => self.first_non_blank(min(count - 1, last_row)),
+            Motion::Lines => self.first_non_blank(min(row + count.saturating_sub(1), last_row)),
+            Motion::MatchingBracket => self
+                .matching_brackets()
+                .map_or(offset, |(_, matching)| matching),
+        }
+    }
+
+    /// Switches the mode of the modal editing, a visual mode selects from the cursor on.
+    fn set_mode(&mut self, mode: Mode) -> Callback {
+        let Some(vim) = &mut self.vim else {
+            return Callback::dummy();
+        };
+        let was_visual = vim.mode.is_visual();
+        vim.set_mode(mode);
+        // the commands of the other modes only apply to the primary cursor
+        if mode != Mode::Insert {
+            self.cursors.clear();
+        }
+        if !mode.is_visual() {
+            self.selection = None;
+        } else if !was_visual {
+            self.selection = Some(self.cursor.char_offset);
+        }
+
+        match self.on_mode.clone() {
+            Some(cb) => Callback::from_fn(move |siv| cb(siv, mode)),
+            None => Callback::dummy(),
+        }
+    }
+
+    /// Handles the keys of the modal editing, `None` if the event is handled as usual.
+    fn on_modal_event(&mut self, event: &Event) -> Option<EventResult> {
+        let vim = self.vim.as_mut()?;
+        if vim.mode == Mode::Insert {
+            if *event != Event::Key(Key::Esc) {
+                return None;
+            }
+            self.buffer.lock().unwrap().history.seal();
+            // Like in Vim, the cursor moves onto the last inserted character.
+            let (line_start, _) = self.line_bounds(self.selected_row());
+            if self.cursor.char_offset > line_start {
+                self.move_left();
+            }
+            return Some(EventResult::Consumed(Some(self.set_mode(Mode::Normal))));
+        }
+        if matches!(event, Event::Mouse { .. }) {
+            return None;
+        }
+
+        match vim.parse(event) {
+            Parsed::Command(command) => {
+                self.buffer.lock().unwrap().history.seal();
+                Some(EventResult::Consumed(Some(self.run_command(command))))
+            }
+            Parsed::Pending => Some(EventResult::consumed()),
+            // Typing doesn't insert anything outside of the insert mode.
+            Parsed::Unknown if matches!(event, Event::Char(_)) => Some(EventResult::consumed()),
+            Parsed::Unknown => None,
+        }
+    }
+
+    /// Runs a command of the normal or visual mode.
+    fn run_command(&mut self, command: Command) -> Callback {
+        let visual = self.mode().is_some_and(Mode::is_visual);
+        match command {
+            Command::Move(motion, count) => {
+                if !visual {
+                    self.selection = None;
+                }
+                match motion {
+                    Motion::Up => {
+                        (0..count).for_each(|_| drop(self.move_up()));
+                        self.on_interact_callback().unwrap_or_else(Callback::dummy)
+                    }
+                    Motion::Down => {
+                        (0..count).for_each(|_| drop(self.move_down()));
+                        self.on_interact_callback().unwrap_or_else(Callback::dummy)
+                    }
+                    Motion::Right | Motion::LineEnd => {
+                        // The cursor stays on the last character, the line end is only reached by operators.
+                        let (line_start, line_end) = self.line_bounds(self.selected_row());
+                        let last = match line_end > line_start {
+                            true => self.prev_grapheme(line_end),
+                            false => line_start,
+                        };
+                        self.set_cursor_from_char_offset(min(
+                            self.motion_target(motion, count),
+                            last,
+                        ))
+                    }
+                    _ => self.set_cursor_from_char_offset(self.motion_target(motion, count)),
+                }
+            }
+            Command::Operate(operator, motion, count) => {
+                let offset = self.cursor.char_offset;
+                let (range, linewise) = if motion.is_linewise() {
+                    let row = self.selected_row();
+                    let target = self.content.char_to_line(self.motion_target(motion, count));
+                    (self.lines_range(min(row, target), max(row, target)), true)
+                } else {
+                    // `cw` on a word changes only the word, like `ce`
+                    let motion = match motion {
+                        Motion::WordStart
+                            if operator == Operator::Change && self.class_at(offset) != 0 =>
+                        {
+                            Motion::WordEnd
+                        }
+                        motion => motion,
+                    };
+                    let target = self.motion_target(motion, count);
+                    let end = max(offset, target);
+                    let end = match motion.is_inclusive() {
+                        true => min(end + 1, self.content.len_chars()),
+                        false => end,
+                    };
+                    (min(offset, target)..end, false)
+                };
+                self.operate(operator, range, linewise)
+            }
+            Command::OperateSelection(operator) => {
+                let linewise = self.mode() == Some(Mode::VisualLine);
+                let range = self.selection_range();
+                let callback = self.set_mode(Mode::Normal);
+                match range {
+                    Some(range) => chain(callback, self.operate(operator, range, linewise)),
+                    None => callback,
+                }
+            }
+            Command::Insert(at) => {
+                let row = self.selected_row();
+                let (line_start, line_end) = self.line_bounds(row);
+                let callback = match at {
+                    InsertAt::Cursor => Callback::dummy(),
+                    InsertAt::After => {
+                        self.set_cursor_from_char_offset(min(self.cursor.char_offset + 1, line_end))
+                    }
+                    InsertAt::LineStart => {
+                        self.set_cursor_from_char_offset(self.first_non_blank(row))
+                    }
+                    InsertAt::LineEnd => self.set_cursor_from_char_offset(line_end),
+                    InsertAt::LineBelow => {
+                        self.set_cursor_from_char_offset(line_end);
+                        self.newline()
+                    }
+                    InsertAt::LineAbove => {
+                        // The new line gets the indentation of the current one.
+                        let indentation = self.first_non_blank(row) - line_start;
+                        self.set_cursor_from_char_offset(line_start + indentation);
+                        let callback = self.newline();
+                        self.set_cursor_from_char_offset(line_start + indentation);
+                        callback
+                    }
+                };
+                chain(callback, self.set_mode(Mode::Insert))
+            }
+            Command::SwitchMode(mode) => self.set_mode(mode),
+            Command::Paste { after } => self.paste_register(after),
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            Command::HalfPageDown => self.half_page(true),
+            Command::HalfPageUp => self.half_page(false),
+            Command::Find => Callback::from_fn(|siv| commands::run(siv, "find", &[])),
+            Command::SearchNext { forward } => {
+                let callback = self.search_next(forward);
+                self.selection = None;
+                callback
+            }
+            Command::Commands => {
+                Callback::from_fn(|siv| quick_access::open_with(siv, ">").handle(siv))
+            }
+        }
+    }
+
+    /// Applies `operator` to the characters of `range`, which spans whole lines if `linewise`.
+    fn operate(&mut self, operator: Operator, range: Range<usize>, linewise: bool) -> Callback {
+        let mut text = self.content.slice(range.clone()).to_string();
+        if linewise && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let row = self.content.char_to_line(range.start);
+
+        match operator {
+            Operator::Yank => {
+                crate::clipboard::set_content(text).unwrap_or_else(|e| error!("{e}"));
+                match linewise {
+                    true => self.on_interact_callback().unwrap_or_else(Callback::dummy),
+                    false => self.set_cursor_from_char_offset(range.start),
+                }
+            }
+            Operator::Delete | Operator::Change => {
+                // nothing to remove, e.g. `x` on an empty line
+                if range.is_empty() && !(linewise && operator == Operator::Change) {
+                    return match operator {
+                        Operator::Change => self.set_mode(Mode::Insert),
+                        _ => Callback::dummy(),
+                    };
+                }
+                crate::clipboard::set_content(text).unwrap_or_else(|e| error!("{e}"));
+                self.record(EditKind::Other);
+                let mut range = range;
+                if linewise && operator == Operator::Change {
+                    // the lines are replaced by an empty one
+                    if !range.is_empty() && self.content.char(range.end - 1) == '\n' {
+                        range.end -= 1;
+                    }
+                } else if linewise && range.end == self.content.len_chars() && range.start > 0 {
+                    // the last line takes the line break before it
+                    range.start -= 1;
+                }
+                self.remove_range(range);
+
+                let callback = if operator == Operator::Change {
+                    self.set_mode(Mode::Insert)
+                } else if linewise {
+                    let row = min(row, self.content.len_lines().saturating_sub(1));
+                    self.set_cursor_from_char_offset(self.first_non_blank(row));
+                    Callback::dummy()
+                } else {
+                    Callback::dummy()
+                };
+                chain(
+                    self.on_edit_callback().unwrap_or_else(Callback::dummy),
+                    callback,
+                )
+            }
+            Operator::Indent | Operator::Dedent => {
+                self.selection = Some(range.start);
+                self.set_cursor_from_char_offset(range.end);
+                let callback = self.tabulator(operator == Operator::Indent);
+                self.selection = None;
+                self.set_cursor_from_char_offset(self.first_non_blank(row));
+                callback
+            }
+        }
+    }
+
+    /// Pastes the clipboard after or before the cursor, lines are pasted below or above it.
+    fn paste_register(&mut self, after: bool) -> Callback {
+        let Ok(text) = crate::clipboard::get_content() else {
+            return Callback::dummy();
+        };
+        let row = self.selected_row();
+        let (_, line_end) = self.line_bounds(row);
+
+        self.record(EditKind::Other);
+        self.selection = None;
+        self.invalidate_highlighting(row);
+        if text.ends_with('\n') {
+            let row = if after { row + 1 } else { row };
+            if row < self.content.len_lines() {
+                self.insert_chars(self.content.line_to_char(row), &text);
+            } else {
+                // the last line has no line break to insert after
+                let text = format!("\n{}", text.trim_end_matches('\n'));
+                self.insert_chars(self.content.len_chars(), &text);
+            }
+            self.set_cursor_from_char_offset(self.first_non_blank(row));
+        } else {
+            let offset = match after {
+                true => min(self.cursor.char_offset + 1, line_end),
+                false => self.cursor.char_offset,
+            };
+            self.insert_chars(offset, &text);
+            let len = text.chars().count();
+            self.set_cursor_from_char_offset(offset + len.saturating_sub(1));
+        }
+        self.compute_max_content_width(None);
+        self.on_edit_callback().unwrap_or_else(Callback::dummy)
+    }
+
     fn on_interact_callback(&self) -> Option<Callback> {
         self.on_interact.clone().map(|cb| {
             let content = self.content.clone();
@@ -730,7 +2508,13 @@ impl EditArea {
         })
     }
 
-    fn on_edit_callback(&self) -> Option<Callback> {
+    /// Run any callback after editing, also refreshing the search matches.
+    fn on_edit_callback(&mut self) -> Option<Callback> {
+        if let (Some(search), Some(edit)) = (&mut self.search, self.edit.take()) {
+            search.update(&self.content, edit);
+        }
+        self.buffer.lock().unwrap().content = self.content.clone();
+
         self.on_edit.clone().map(|cb| {
             let content = self.content.clone();
             let scroll_offset = self.scroll_core.content_viewport().top_left();
@@ -748,93 +2532,256 @@ impl EditArea {
             return EventResult::Ignored;
         }
 
+        if let Some(result) = self.on_modal_event(&event) {
+            return result;
+        }
+
+        // Only continuously typing or deleting is grouped into one undo step.
+        if !matches!(
+            event,
+            Event::Char(_) | Event::Key(Key::Backspace) | Event::Key(Key::Del)
+        ) {
+            self.buffer.lock().unwrap().history.seal();
+        }
+
+        // `Esc` goes back to a single cursor.
+        if event == Event::Key(Key::Esc) && !self.cursors.is_empty() {
+            self.cursors.clear();
+            return EventResult::consumed();
+        }
+
+        if let Some(&action) = self.keymap.get(&event) {
+            return self.run_action(action);
+        }
+
+        if let Event::Mouse {
+            event: MouseEvent::Press(MouseButton::Left),
+            position,
+            offset,
+        } = event
+        {
+            if backend::alt_held() {
+                return EventResult::Consumed(Some(self.toggle_cursor(position, offset)));
+            }
+        }
+
+        // Moving the cursor without extending the selection discards it.
+        if matches!(
+            event,
+            Event::Key(Key::Up | Key::Down | Key::Left | Key::Right | Key::Home | Key::End)
+        ) {
+            self.discard_selections();
+        }
+        if let Event::Mouse {
+            event: MouseEvent::Press(_),
+            ..
+        } = event
+        {
+            self.selection = None;
+            self.cursors.clear();
+        }
+        let multiple = !self.cursors.is_empty();
+
         match event {
             Event::Char(ch) => {
-                return EventResult::Consumed(Some(self.insert(ch)));
+                return EventResult::Consumed(Some(self.at_each_cursor(|area| area.insert(ch))));
             }
             Event::Key(Key::Enter) => {
-                return EventResult::Consumed(Some(self.insert('\n')));
+                return EventResult::Consumed(Some(self.at_each_cursor(Self::newline)));
             }
-            Event::Key(Key::Backspace) if self.cursor.byte_offset > 0 => {
-                return EventResult::Consumed(Some(self.backspace()));
+            Event::Key(Key::Home) => {
+                return EventResult::Consumed(Some(
+                    self.at_each_cursor(|area| area.move_cursor_end(Key::Left)),
+                ));
             }
-            Event::Key(Key::Del) if self.cursor.byte_offset < self.content.len_bytes() => {
-                return EventResult::Consumed(Some(self.delete()));
+            Event::Key(Key::End) => {
+                return EventResult::Consumed(Some(
+                    self.at_each_cursor(|area| area.move_cursor_end(Key::Right)),
+                ));
+            }
+            Event::Key(Key::Backspace)
+                if multiple || self.cursor.byte_offset > 0 || self.selection_range().is_some() =>
+            {
+                return EventResult::Consumed(Some(self.at_each_cursor(Self::backspace)));
+            }
+            Event::Key(Key::Del)
+                if multiple
+                    || self.cursor.byte_offset < self.content.len_bytes()
+                    || self.selection_range().is_some() =>
+            {
+                return EventResult::Consumed(Some(self.at_each_cursor(Self::delete)));
             }
             Event::Key(Key::Up) => {
-                if self.selected_row() > 0 {
-                    return EventResult::Consumed(Some(self.move_up()));
+                if multiple || self.selected_row() > 0 {
+                    return EventResult::Consumed(Some(self.at_each_cursor(Self::move_up)));
                 }
             }
             Event::Key(Key::Down) => {
-                if self.selected_row() + 1 < self.content.len_lines() {
-                    return EventResult::Consumed(Some(self.move_down()));
+                if multiple || self.selected_row() + 1 < self.content.len_lines() {
+                    return EventResult::Consumed(Some(self.at_each_cursor(Self::move_down)));
                 }
             }
             Event::Key(Key::Left) => {
-                if self.cursor.byte_offset > 0 {
-                    return EventResult::Consumed(Some(self.move_left()));
+                if multiple || self.cursor.byte_offset > 0 {
+                    return EventResult::Consumed(Some(self.at_each_cursor(Self::move_left)));
                 }
             }
             Event::Key(Key::Right) => {
-                if self.cursor.byte_offset < self.content.len_bytes() {
-                    return EventResult::Consumed(Some(self.move_right()));
+                if multiple || self.cursor.byte_offset < self.content.len_bytes() {
+                    return EventResult::Consumed(Some(self.at_each_cursor(Self::move_right)));
                 }
             }
-            Event::Shift(Key::Left) => {
-                return EventResult::Consumed(Some(self.move_cursor_end(Key::Left)));
-            }
-            Event::Shift(Key::Right) => {
-                return EventResult::Consumed(Some(self.move_cursor_end(Key::Right)));
-            }
-            Event::Key(Key::PageUp) => {
-                return EventResult::Consumed(Some(self.page_up()));
-            }
-            Event::Key(Key::PageDown) => {
-                return EventResult::Consumed(Some(self.page_down()));
-            }
-            Event::Shift(Key::PageUp) => {
-                return EventResult::Consumed(Some(self.set_curser_from_byte_offset(0)));
-            }
-            Event::Shift(Key::PageDown) => {
-                return EventResult::Consumed(Some(
-                    self.set_curser_from_byte_offset(self.content.len_bytes()),
-                ));
-            }
             Event::Mouse {
                 event: MouseEvent::Press(_),
                 position,
                 offset,
             } => {
-                return EventResult::Consumed(Some(self.move_mouse(position, offset)));
+                let callback = self.move_mouse(position, offset);
+                // Dragging from here on extends the selection.
+                self.selection = Some(self.cursor.char_offset);
+                return EventResult::Consumed(Some(callback));
             }
-            Event::CtrlChar('c') => self.copy(),
-            Event::CtrlChar('v') => {
-                return EventResult::Consumed(Some(self.paste()));
+            Event::Mouse {
+                event: MouseEvent::Hold(_),
+                position,
+                offset,
+            } => {
+                self.extend_selection();
+                return EventResult::Consumed(Some(self.move_mouse(position, offset)));
             }
-            Event::CtrlChar('x') => {
-                return EventResult::Consumed(Some(self.cut()));
+            _ => return EventResult::Ignored,
+        }
+
+        EventResult::consumed()
+    }
+
+    /// Runs the editor `action`, bound to a key via the keymap
+    fn run_action(&mut self, action: Action) -> EventResult {
+        // Moving the cursor without extending the selection discards it.
+        if matches!(
+            action,
+            Action::LineStart
+                | Action::LineEnd
+                | Action::Up
+                | Action::Down
+                | Action::Left
+                | Action::Right
+                | Action::PageUp
+                | Action::PageDown
+                | Action::FileStart
+                | Action::FileEnd
+                | Action::JumpToBracket
+                | Action::WordLeft
+                | Action::WordRight
+                | Action::ParagraphUp
+                | Action::ParagraphDown
+        ) {
+            self.discard_selections();
+        }
+        // Only these actions apply to every cursor, the others go back to a single one.
+        if !matches!(
+            action,
+            Action::Copy
+                | Action::Paste
+                | Action::MoveLineUp
+                | Action::MoveLineDown
+                | Action::LineStart
+                | Action::LineEnd
+                | Action::SelectUp
+                | Action::SelectDown
+                | Action::SelectLeft
+                | Action::SelectRight
+                | Action::Indent
+                | Action::Dedent
+                | Action::Up
+                | Action::Down
+                | Action::Left
+                | Action::Right
+                | Action::DeleteChar
+                | Action::AddCursorUp
+                | Action::AddCursorDown
+                | Action::SelectNext
+                | Action::WordLeft
+                | Action::WordRight
+                | Action::DeleteWordLeft
+                | Action::DeleteWordRight
+                | Action::ParagraphUp
+                | Action::ParagraphDown
+        ) {
+            self.cursors.clear();
+        }
+        let multiple = !self.cursors.is_empty();
+
+        let callback = match action {
+            Action::SelectAll => self.select_all(),
+            Action::Copy => {
+                self.copy();
+                return EventResult::consumed();
             }
-            Event::Shift(Key::Up) => {
-                return EventResult::Consumed(Some(self.move_line(Key::Up)));
+            Action::Paste => self.paste(),
+            Action::Cut => self.cut(),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::MoveLineUp => self.move_line(Key::Up),
+            Action::MoveLineDown => self.move_line(Key::Down),
+            Action::LineStart => self.at_each_cursor(|area| area.move_cursor_end(Key::Left)),
+            Action::LineEnd => self.at_each_cursor(|area| area.move_cursor_end(Key::Right)),
+            Action::PageUp => self.page_up(),
+            Action::PageDown => self.page_down(),
+            Action::FileStart => self.set_curser_from_byte_offset(0),
+            Action::FileEnd => self.set_curser_from_byte_offset(self.content.len_bytes()),
+            Action::SelectUp => self.at_each_cursor(|area| {
+                area.extend_selection();
+                area.move_up()
+            }),
+            Action::SelectDown => self.at_each_cursor(|area| {
+                area.extend_selection();
+                area.move_down()
+            }),
+            Action::SelectLeft => self.at_each_cursor(|area| {
+                area.extend_selection();
+                area.move_left()
+            }),
+            Action::SelectRight => self.at_each_cursor(|area| {
+                area.extend_selection();
+                area.move_right()
+            }),
+            Action::Indent => self.tabulator(true),
+            Action::Dedent => self.tabulator(false),
+            Action::Up if multiple || self.selected_row() > 0 => self.at_each_cursor(Self::move_up),
+            Action::Down if multiple || self.selected_row() + 1 < self.content.len_lines() => {
+                self.at_each_cursor(Self::move_down)
             }
-            Event::Shift(Key::Down) => {
-                return EventResult::Consumed(Some(self.move_line(Key::Down)));
+            Action::Left if multiple || self.cursor.char_offset > 0 => {
+                self.at_each_cursor(Self::move_left)
             }
-            Event::Key(Key::Tab) => {
-                return EventResult::Consumed(Some(self.tabulator(true)));
+            Action::Right if multiple || self.cursor.char_offset < self.content.len_chars() => {
+                self.at_each_cursor(Self::move_right)
             }
-            Event::Shift(Key::Tab) => {
-                return EventResult::Consumed(Some(self.tabulator(false)));
+            Action::DeleteChar => self.at_each_cursor(Self::delete),
+            Action::DeleteToLineEnd => self.delete_to_line_end(),
+            Action::AddCursorUp => self.add_cursor_vertically(Key::Up),
+            Action::AddCursorDown => self.add_cursor_vertically(Key::Down),
+            Action::SelectNext => self.select_next(),
+            Action::JumpToBracket => self.jump_to_bracket(),
+            Action::WordLeft => self.at_each_cursor(|area| area.move_word(Key::Left)),
+            Action::WordRight => self.at_each_cursor(|area| area.move_word(Key::Right)),
+            Action::DeleteWordLeft => self.at_each_cursor(|area| area.delete_word(Key::Left)),
+            Action::DeleteWordRight => self.at_each_cursor(|area| area.delete_word(Key::Right)),
+            Action::ParagraphUp => self.at_each_cursor(|area| area.move_paragraph(Key::Up)),
+            Action::ParagraphDown => self.at_each_cursor(|area| area.move_paragraph(Key::Down)),
+            Action::Up | Action::Down | Action::Left | Action::Right => {
+                return EventResult::Ignored
             }
-            _ => return EventResult::Ignored,
-        }
-
-        EventResult::consumed()
+        };
+        EventResult::Consumed(Some(callback))
     }
 
     /// Compute the required size for the content.
     fn inner_required_size(&mut self, vec: Vec2) -> Vec2 {
+        if self.soft_wrap {
+            return Vec2::new(vec.x, self.visual_rows());
+        }
         Vec2::new(
             max(self.max_content_width + 1, vec.x),
             self.content.len_lines(),
@@ -842,44 +2789,56 @@ impl EditArea {
     }
 
     fn inner_important_area(&self, _: Vec2) -> Rect {
+        let position = self.visual_position(self.cursor.char_offset);
         // The important area is a single character
         let char_width = if self.cursor.char_offset >= self.content.len_chars() {
             // If we're are the end of the content, it'll be a space
             1
         } else {
-            // Otherwise it's the selected grapheme
-            let start = self.cursor.char_offset;
-            let end = start + 1;
-            self.content.slice(start..end).len_chars()
+            // Otherwise it's the selected character, a tab reaches to the next tab stop
+            match self.content.char(self.cursor.char_offset) {
+                '\t' => grapheme_width("\t", position.x, self.tab_width),
+                _ => 1,
+            }
         };
 
-        Rect::from_size(
-            Vec2::new(self.selected_col(), self.selected_row()),
-            (
-                char_width + self.content.len_lines().to_string().len() + 2,
-                1,
-            ),
-        )
+        Rect::from_size(position, (char_width + self.gutter_width() + 1, 1))
     }
 }
 
 impl View for EditArea {
     fn draw(&self, printer: &Printer) {
+        let brackets = if printer.focused && self.enabled {
+            self.matching_brackets()
+        } else {
+            None
+        };
         printer.with_style(PaletteStyle::Primary, |printer| {
-            scroll::draw_lines(self, printer, |edit_area, printer, i| {
+            scroll::draw_lines(self, printer, |edit_area, printer, row| {
+                let (i, row_range, indent) = edit_area.visual_row(row);
                 let row_start = self.content.line_to_byte(i);
                 let text = edit_area.content.line(i).to_string();
 
-                let mut highlighter = HighlightLines::new(&edit_area.synref, &edit_area.theme);
-                let styled = cursive_syntect::parse(&text, &mut highlighter, &edit_area.syntax)
-                    .unwrap_or_default();
+                let mut styled = StyledString::new();
+                let mut highlighter = edit_area.highlighter.lock().unwrap();
+                for (style, range) in
+                    highlighter.line(&edit_area.content, i, &edit_area.syntax, &edit_area.theme)
+                {
+                    styled.append_styled(&text[range.clone()], theme::translate_style(*style));
+                }
+                drop(highlighter);
 
                 // Check if file needs to be numbered.
-                let numbering = if printer.enabled && edit_area.enabled {
+                let numbering = if printer.enabled && edit_area.enabled && edit_area.line_numbers {
                     // Calculate max digits for better visual representation.
                     let max_lines_count_digits = edit_area.content.len_lines().to_string().len();
 
-                    let line_number = format!("{:width$} ", i + 1, width = max_lines_count_digits);
+                    // Rows continuing a wrapped line have no number.
+                    let line_number = if row_range.start == edit_area.content.line_to_char(i) {
+                        format!("{:width$} ", i + 1, width = max_lines_count_digits)
+                    } else {
+                        " ".repeat(max_lines_count_digits + 1)
+                    };
 
                     let number_style = if i == edit_area.selected_row() {
                         Style::default()
@@ -891,28 +2850,98 @@ impl View for EditArea {
                     SpannedString::default()
                 };
 
-                let line = StyledString::concatenate(vec![numbering.clone(), styled]);
-
                 let mut x = 0;
-                for span in line.spans() {
+                for span in numbering.spans() {
+                    printer.with_style(
+                        ColorStyle::new(span.attr.color.front, PaletteColor::Background),
+                        |printer| {
+                            printer.print((x, 0), span.content);
+                        },
+                    );
+                    x += span.content.width();
+                }
+                x += indent;
+
+                let selections = edit_area
+                    .selection_range()
+                    .into_iter()
+                    .chain(edit_area.cursors.iter().map(|&cursor| selected(cursor)))
+                    .collect::<Vec<_>>();
+                let selection_color = edit_area
+                    .theme
+                    .settings
+                    .selection
+                    .map_or(Color::Light(BaseColor::Black), theme::translate_color);
+                let matches = edit_area.search.as_ref().map_or(&[][..], |search| {
+                    search.matches_in(row_start..row_start + text.len())
+                });
+                let match_color = edit_area
+                    .theme
+                    .settings
+                    .find_highlight
+                    .map_or(Color::Dark(BaseColor::Yellow), theme::translate_color);
+                let bracket_color = edit_area
+                    .theme
+                    .settings
+                    .brackets_background
+                    .map_or(Color::Light(BaseColor::Black), theme::translate_color);
+
+                let mut char_offset = edit_area.content.line_to_char(i);
+                let mut byte_offset = row_start;
+                for span in styled.spans() {
                     let span_text = span.content;
                     let span_style = span.attr.color.front;
                     for grapheme in span_text.graphemes(true) {
+                        // Only the graphemes of this row of a wrapped line are drawn.
+                        if !row_range.contains(&char_offset) {
+                            char_offset += grapheme.chars().count();
+                            byte_offset += grapheme.len();
+                            continue;
+                        }
+                        let background = if selections
+                            .iter()
+                            .any(|selection| selection.contains(&char_offset))
+                        {
+                            selection_color.into()
+                        } else if matches.iter().any(|m| m.contains(&byte_offset)) {
+                            match_color.into()
+                        } else if brackets.is_some_and(|(bracket, matching)| {
+                            char_offset == bracket || char_offset == matching
+                        }) {
+                            bracket_color.into()
+                        } else {
+                            ColorType::from(PaletteColor::Background)
+                        };
+                        char_offset += grapheme.chars().count();
+                        byte_offset += grapheme.len();
+
                         // Check for special characters and print faded.
                         if let Some(special) = special_character(grapheme) {
+                            // Tabs are padded to the next tab stop.
+                            let width = grapheme_width(
+                                grapheme,
+                                x - numbering.width(),
+                                edit_area.tab_width,
+                            );
                             printer.with_style(
-                                ColorStyle::new(
-                                    Color::Light(BaseColor::Black),
-                                    PaletteColor::Background,
-                                ),
+                                ColorStyle::new(Color::Light(BaseColor::Black), background),
                                 |printer| {
                                     printer.print((x, 0), special);
+                                    printer.print_hline((x + 1, 0), width - 1, " ");
+                                },
+                            );
+                            x += width;
+                        } else if grapheme.ends_with('\n') {
+                            // Selected line breaks are shown as a space.
+                            printer.with_style(
+                                ColorStyle::new(span_style, background),
+                                |printer| {
+                                    printer.print((x, 0), " ");
                                 },
                             );
-                            x += 1;
                         } else {
                             printer.with_style(
-                                ColorStyle::new(span_style, PaletteColor::Background),
+                                ColorStyle::new(span_style, background),
                                 |printer| {
                                     printer.print((x, 0), grapheme);
                                 },
@@ -922,12 +2951,16 @@ impl View for EditArea {
                     }
                 }
 
-                if printer.focused
-                    && i == edit_area.selected_row()
-                    && printer.enabled
-                    && edit_area.enabled
-                {
-                    let cursor_offset = edit_area.cursor.byte_offset - row_start;
+                if !(printer.focused && printer.enabled && edit_area.enabled) {
+                    return;
+                }
+                let cursors = edit_area.all_cursors().map(|(cursor, _)| cursor);
+                for cursor in cursors.filter(|cursor| edit_area.row_at(cursor.byte_offset) == i) {
+                    let position = edit_area.visual_position(cursor.char_offset);
+                    if position.y != row {
+                        continue;
+                    }
+                    let cursor_offset = cursor.byte_offset - row_start;
                     let mut c = StyledString::new();
                     let mut selected_char = if cursor_offset == text.len()
                         || (text[cursor_offset..].contains("\n")
@@ -942,8 +2975,7 @@ impl View for EditArea {
                         selected_char = special;
                     }
                     c.append_styled(selected_char, Style::primary().combine(Effect::Reverse));
-                    let offset = text[..cursor_offset].width() + numbering.width();
-                    printer.print_styled((offset, 0), &c);
+                    printer.print_styled((position.x + numbering.width(), 0), &c);
                 }
             });
         });
@@ -971,6 +3003,14 @@ impl View for EditArea {
     }
 
     fn layout(&mut self, size: Vec2) {
+        // The scrollbar is always left out, so showing it doesn't change the wrapping.
+        let width = size
+            .x
+            .saturating_sub(self.gutter_width() + SCROLLBAR_WIDTH + 1);
+        self.wrap
+            .get_mut()
+            .unwrap()
+            .set_width(width, self.tab_width);
         scroll::layout(self, size, true, |_s, _size| (), Self::inner_required_size);
     }
 
@@ -978,3 +3018,173 @@ impl View for EditArea {
         scroll::important_area(self, size, Self::inner_important_area)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A wide character, an emoji joined by zero width joiners, a combining accent and a tab
+    const MIXED: &str = "a漢👨‍👩‍👧e\u{301}\tb";
+
+    fn area(content: &str) -> EditArea {
+        EditArea::new(&Theme::default()).content(content)
+    }
+
+    /// The grapheme boundaries of `MIXED` as character offsets
+    const BOUNDARIES: [usize; 7] = [0, 1, 2, 7, 9, 10, 11];
+
+    #[test]
+    fn mixed_width() {
+        // `a` 1, `漢` 2, the family 2, `é` 1, the tab to 8, `b` 1
+        assert_eq!(line_width(MIXED, 4), 9);
+        assert_eq!(line_width(MIXED, 8), 9);
+        assert_eq!(line_width("\tb", 4), 5);
+        assert_eq!(grapheme_prefix_length(MIXED, 0, 4), 0);
+        assert_eq!(grapheme_prefix_length(MIXED, 2, 4), 1);
+        assert_eq!(grapheme_prefix_length(MIXED, 3, 4), 2);
+        assert_eq!(grapheme_prefix_length(MIXED, 5, 4), 7);
+        assert_eq!(grapheme_prefix_length(MIXED, 6, 4), 9);
+        assert_eq!(grapheme_prefix_length(MIXED, 7, 4), 9);
+        assert_eq!(grapheme_prefix_length(MIXED, 8, 4), 10);
+        assert_eq!(grapheme_prefix_length(MIXED, 100, 4), 11);
+    }
+
+    #[test]
+    fn grapheme_boundaries() {
+        for pair in BOUNDARIES.windows(2) {
+            assert_eq!(next_grapheme_boundary(MIXED, pair[0]), pair[1]);
+            assert_eq!(prev_grapheme_boundary(MIXED, pair[1]), pair[0]);
+        }
+        // offsets inside of a grapheme
+        assert_eq!(next_grapheme_boundary(MIXED, 3), 7);
+        assert_eq!(prev_grapheme_boundary(MIXED, 3), 2);
+        assert_eq!(next_grapheme_boundary("a\r\nb", 1), 3);
+        assert_eq!(prev_grapheme_boundary("a\r\nb", 3), 1);
+    }
+
+    #[test]
+    fn move_by_graphemes() {
+        let mut area = area(&format!("{MIXED}\r\nx"));
+        let mut offsets = vec![area.cursor.char_offset];
+        while area.cursor.char_offset < area.content.len_chars() {
+            area.move_right();
+            offsets.push(area.cursor.char_offset);
+        }
+        let mut expected = BOUNDARIES.to_vec();
+        expected.extend([13, 14]);
+        assert_eq!(offsets, expected);
+
+        offsets.clear();
+        while area.cursor.char_offset > 0 {
+            area.move_left();
+            offsets.push(area.cursor.char_offset);
+        }
+        expected.reverse();
+        assert_eq!(offsets, expected[1..]);
+    }
+
+    #[test]
+    fn remove_graphemes() {
+        let mut area = area(MIXED);
+        area.set_cursor_from_char_offset(2);
+        area.delete();
+        assert_eq!(area.content, "a漢e\u{301}\tb");
+        area.set_cursor_from_char_offset(4);
+        area.backspace();
+        assert_eq!(area.content, "a漢\tb");
+        assert_eq!(area.cursor.char_offset, 2);
+        assert_eq!(area.cursor.column, 3);
+    }
+
+    #[test]
+    fn sticky_column() {
+        let mut area = area("漢字漢字\n\nab\n\tx\n漢字漢字");
+        area.set_cursor_from_char_offset(3);
+        assert_eq!(area.cursor.column, 6);
+
+        area.move_down();
+        assert_eq!((area.cursor.row, area.cursor.column), (1, 0));
+        area.move_down();
+        assert_eq!((area.cursor.row, area.cursor.column), (2, 2));
+        // the tab reaches to column 4, `x` is at the sticky column
+        area.move_down();
+        assert_eq!((area.cursor.row, area.cursor.column), (3, 5));
+        area.move_down();
+        assert_eq!((area.cursor.row, area.cursor.column), (4, 6));
+        area.move_up();
+        area.move_up();
+        area.move_up();
+        area.move_up();
+        assert_eq!(area.cursor.char_offset, 3);
+
+        // a column in the middle of a wide character is before it
+        area.set_cursor_from_char_offset(11);
+        assert_eq!((area.cursor.row, area.cursor.column), (3, 5));
+        area.move_up();
+        area.move_up();
+        area.move_up();
+        assert_eq!((area.cursor.char_offset, area.cursor.column), (2, 4));
+        area.move_down();
+        area.move_down();
+        area.move_down();
+        assert_eq!(area.cursor.char_offset, 11);
+
+        // moving horizontally sets a new column, which is inside of the tab below
+        area.move_up();
+        area.move_left();
+        assert_eq!(area.cursor.sticky_column, None);
+        area.move_down();
+        assert_eq!((area.cursor.row, area.cursor.column), (3, 0));
+    }
+
+    #[test]
+    fn sticky_column_of_cursors() {
+        let mut area = area("long line\nab\nlong line");
+        area.set_cursor_from_char_offset(7);
+        area.add_cursor_vertically(Key::Down);
+        area.add_cursor_vertically(Key::Down);
+        assert_eq!(area.cursor.row, 2);
+        assert_eq!(area.cursor.column, 7);
+    }
+
+    #[test]
+    fn dedent_closing_bracket() {
+        let typed = |content: &str| {
+            let mut area = area(content);
+            area.set_cursor_from_char_offset(area.content.len_chars());
+            area.insert('}');
+            area.content.to_string()
+        };
+        assert_eq!(typed("{\n    a\n    "), "{\n    a\n}");
+        assert_eq!(
+            typed("    if a {\n        b\n        "),
+            "    if a {\n        b\n    }"
+        );
+        // already at the indentation of the opening bracket
+        assert_eq!(typed("{\n    a\n"), "{\n    a\n}");
+        assert_eq!(typed("    if a {\n    "), "    if a {\n    }");
+        assert_eq!(typed("\tif a {\n\t\t"), "\tif a {\n\t}");
+        // without an opening bracket, one level less than the previous line
+        assert_eq!(typed("        a\n\n        "), "        a\n\n    }");
+        assert_eq!(typed("        a\n    "), "        a\n    }");
+    }
+
+    #[test]
+    fn normal_mode_line_end() {
+        let mut area = area("ab\n\ncd");
+        area.vim = Some(Vim::default());
+        area.run_command(Command::Move(Motion::Right, 5));
+        assert_eq!(area.cursor.char_offset, 1);
+        area.run_command(Command::Move(Motion::LineEnd, 1));
+        assert_eq!(area.cursor.char_offset, 1);
+
+        // `x` on an empty line changes nothing and records no undo step
+        area.set_cursor_from_char_offset(3);
+        area.run_command(Command::Move(Motion::Right, 1));
+        assert_eq!(area.cursor.char_offset, 3);
+        area.run_command(Command::Operate(Operator::Delete, Motion::Right, 1));
+        assert_eq!(area.content, "ab\n\ncd");
+        let snapshot = area.snapshot();
+        assert!(area.buffer.lock().unwrap().history.undo(snapshot).is_none());
+    }
+}
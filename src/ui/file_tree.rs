@@ -15,6 +15,8 @@ pub struct TreeEntry {
     pub name: String,
     pub path: PathBuf,
     pub dir: Option<PathBuf>,
+    /// If the directory is currently expanded
+    pub expanded: bool,
 }
 
 impl fmt::Display for TreeEntry {
@@ -37,6 +39,7 @@ fn collect_entries(dir: &PathBuf, entries: &mut Vec<TreeEntry>) -> io::Result<()
                         .unwrap_or_else(|_| String::new()),
                     path: entry.path(),
                     dir: Some(path),
+                    expanded: false,
                 });
             } else if path.is_file() {
                 entries.push(TreeEntry {
@@ -46,6 +49,7 @@ fn collect_entries(dir: &PathBuf, entries: &mut Vec<TreeEntry>) -> io::Result<()
                         .unwrap_or_else(|_| String::new()),
                     path: entry.path(),
                     dir: None,
+                    expanded: false,
                 });
             }
         }
@@ -89,6 +93,32 @@ pub fn expand_tree(
     }
 }
 
+/// Returns all expanded directories that are visible in the tree
+pub fn expanded_dirs(tree: &TreeView<TreeEntry>) -> Vec<PathBuf> {
+    (0..tree.len())
+        .filter_map(|row| tree.borrow_item(row))
+        .filter(|item| item.expanded)
+        .filter_map(|item| item.dir.clone())
+        .collect()
+}
+
+/// Expands the directories `dirs`, parents have to come before their children
+pub fn expand_dirs(tree: &mut TreeView<TreeEntry>, dirs: &[PathBuf]) {
+    for dir in dirs {
+        let row = (0..tree.len()).find(|row| {
+            tree.borrow_item(*row)
+                .is_some_and(|item| item.dir.as_ref() == Some(dir) && !item.expanded)
+        });
+        if let Some(row) = row {
+            expand_tree(tree, row, dir, Placement::LastChild);
+            tree.expand_item(row);
+            if let Some(item) = tree.borrow_item_mut(row) {
+                item.expanded = true;
+            }
+        }
+    }
+}
+
 pub fn load_parent(tree: &mut TreeView<TreeEntry>, dir: &PathBuf) {
     tree.clear();
     expand_tree(tree, 0, dir, Placement::Before);
@@ -102,6 +132,10 @@ pub fn new(parent: &PathBuf) -> ScrollView<NamedView<TreeView<TreeEntry>>> {
     // Stuff that should happen when interacted with a collapse
     tree.set_on_collapse(|siv: &mut Cursive, row, is_collapsed, children| {
         siv.call_on_name("tree", move |tree: &mut TreeView<TreeEntry>| {
+            if let Some(item) = tree.borrow_item_mut(row) {
+                item.expanded = !is_collapsed;
+            }
+
             // Lazily insert directory listings for sub nodes if there weren't already opened
             if !is_collapsed && children == 0 {
                 if let Some(dir) = tree
@@ -0,0 +1,535 @@
+//! Keybindings of the commands and the actions of the editor
+//!
+//! Both have default keybindings given by the preset of the config (`keymap = "emacs"`), which
+//! can be changed in the `keybindings` table of the config by their id, e.g. `undo = "ctrl+z"`.
+//! An empty string removes a keybinding.
+
+use std::collections::{BTreeMap, HashMap};
+
+use cursive::event::{Event, Key};
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Result},
+    ui::commands::{self, Command},
+};
+
+/// An action of the editor which can be bound to a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    SelectAll,
+    Copy,
+    Paste,
+    Cut,
+    Undo,
+    Redo,
+    MoveLineUp,
+    MoveLineDown,
+    LineStart,
+    LineEnd,
+    PageUp,
+    PageDown,
+    FileStart,
+    FileEnd,
+    SelectUp,
+    SelectDown,
+    SelectLeft,
+    SelectRight,
+    Indent,
+    Dedent,
+    Up,
+    Down,
+    Left,
+    Right,
+    DeleteChar,
+    DeleteToLineEnd,
+    AddCursorUp,
+    AddCursorDown,
+    SelectNext,
+    JumpToBracket,
+    WordLeft,
+    WordRight,
+    DeleteWordLeft,
+    DeleteWordRight,
+    ParagraphUp,
+    ParagraphDown,
+}
+
+impl Action {
+    /// All actions in the order they are listed
+    pub const ALL: &'static [Action] = &[
+        Action::Copy,
+        Action::Paste,
+        Action::Cut,
+        Action::SelectAll,
+        Action::SelectUp,
+        Action::SelectDown,
+        Action::SelectLeft,
+        Action::SelectRight,
+        Action::Undo,
+        Action::Redo,
+        Action::MoveLineUp,
+        Action::MoveLineDown,
+        Action::LineStart,
+        Action::LineEnd,
+        Action::PageUp,
+        Action::PageDown,
+        Action::FileStart,
+        Action::FileEnd,
+        Action::Indent,
+        Action::Dedent,
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::DeleteChar,
+        Action::DeleteToLineEnd,
+        Action::AddCursorUp,
+        Action::AddCursorDown,
+        Action::SelectNext,
+        Action::JumpToBracket,
+        Action::WordLeft,
+        Action::WordRight,
+        Action::DeleteWordLeft,
+        Action::DeleteWordRight,
+        Action::ParagraphUp,
+        Action::ParagraphDown,
+    ];
+
+    /// The name used in the config
+    pub fn id(self) -> &'static str {
+        match self {
+            Action::SelectAll => "select_all",
+            Action::Copy => "copy",
+            Action::Paste => "paste",
+            Action::Cut => "cut",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::MoveLineUp => "move_line_up",
+            Action::MoveLineDown => "move_line_down",
+            Action::LineStart => "line_start",
+            Action::LineEnd => "line_end",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::FileStart => "file_start",
+            Action::FileEnd => "file_end",
+            Action::SelectUp => "select_up",
+            Action::SelectDown => "select_down",
+            Action::SelectLeft => "select_left",
+            Action::SelectRight => "select_right",
+            Action::Indent => "indent",
+            Action::Dedent => "dedent",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::DeleteChar => "delete_char",
+            Action::DeleteToLineEnd => "delete_to_line_end",
+            Action::AddCursorUp => "add_cursor_up",
+            Action::AddCursorDown => "add_cursor_down",
+            Action::SelectNext => "select_next",
+            Action::JumpToBracket => "jump_to_bracket",
+            Action::WordLeft => "word_left",
+            Action::WordRight => "word_right",
+            Action::DeleteWordLeft => "delete_word_left",
+            Action::DeleteWordRight => "delete_word_right",
+            Action::ParagraphUp => "paragraph_up",
+            Action::ParagraphDown => "paragraph_down",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Action::SelectAll => "Select All",
+            Action::Copy => "Copying Selection/Line",
+            Action::Paste => "Paste Clipboard",
+            Action::Cut => "Cut Selection/Line",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::MoveLineUp => "Move Line Up",
+            Action::MoveLineDown => "Move Line Down",
+            Action::LineStart => "Move Cursor to SoL",
+            Action::LineEnd => "Move Cursor to EoL",
+            Action::PageUp => "Move Cursor 5x Up",
+            Action::PageDown => "Move Cursor 5x Down",
+            Action::FileStart => "Move Cursor to SoF",
+            Action::FileEnd => "Move Cursor to EoF",
+            Action::SelectUp => "Extend Selection Up",
+            Action::SelectDown => "Extend Selection Down",
+            Action::SelectLeft => "Extend Selection Left",
+            Action::SelectRight => "Extend Selection Right",
+            Action::Indent => "Ident Line(s)",
+            Action::Dedent => "Remove Ident",
+            Action::Up => "Move Cursor Up",
+            Action::Down => "Move Cursor Down",
+            Action::Left => "Move Cursor Left",
+            Action::Right => "Move Cursor Right",
+            Action::DeleteChar => "Delete Character",
+            Action::DeleteToLineEnd => "Delete to EoL",
+            Action::AddCursorUp => "Add Cursor Above",
+            Action::AddCursorDown => "Add Cursor Below",
+            Action::SelectNext => "Select Next Occurrence",
+            Action::JumpToBracket => "Jump to Matching Bracket",
+            Action::WordLeft => "Move Cursor Word Left",
+            Action::WordRight => "Move Cursor Word Right",
+            Action::DeleteWordLeft => "Delete Word Left",
+            Action::DeleteWordRight => "Delete Word Right",
+            Action::ParagraphUp => "Move Cursor Paragraph Up",
+            Action::ParagraphDown => "Move Cursor Paragraph Down",
+        }
+    }
+}
+
+/// The default keybindings, the arrow keys, `Backspace` and `Delete` always work
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    #[default]
+    Default,
+    /// Emacs-like keybindings, e.g. `ctrl+n` moving down or `alt+x` opening the commands
+    Emacs,
+    /// Modal editing like in Vim, the insert mode has the default keybindings
+    Vim,
+}
+
+impl Preset {
+    /// The keybinding of `action` in this preset
+    pub fn action(self, action: Action) -> Option<Event> {
+        if self == Preset::Emacs {
+            let event = match action {
+                Action::SelectAll => Event::AltChar('h'),
+                Action::Copy => Event::AltChar('w'),
+                Action::Paste => Event::CtrlChar('y'),
+                Action::Cut => Event::CtrlChar('w'),
+                // `ctrl+/` and `ctrl+_` as reported by terminals
+                Action::Undo => Event::CtrlChar('7'),
+                Action::Redo => Event::AltChar('_'),
+                Action::LineStart => Event::CtrlChar('a'),
+                Action::LineEnd => Event::CtrlChar('e'),
+                Action::PageUp => Event::AltChar('v'),
+                Action::PageDown => Event::CtrlChar('v'),
+                Action::FileStart => Event::AltChar('<'),
+                Action::FileEnd => Event::AltChar('>'),
+                Action::Up => Event::CtrlChar('p'),
+                Action::Down => Event::CtrlChar('n'),
+                Action::Left => Event::CtrlChar('b'),
+                Action::Right => Event::CtrlChar('f'),
+                Action::DeleteChar => Event::CtrlChar('d'),
+                Action::DeleteToLineEnd => Event::CtrlChar('k'),
+                // `ctrl+d` already deletes
+                Action::SelectNext => Event::AltChar('n'),
+                Action::WordLeft => Event::AltChar('b'),
+                Action::WordRight => Event::AltChar('f'),
+                Action::DeleteWordLeft => Event::Alt(Key::Backspace),
+                Action::DeleteWordRight => Event::AltChar('d'),
+                Action::ParagraphUp => Event::AltChar('{'),
+                Action::ParagraphDown => Event::AltChar('}'),
+                _ => return Preset::Default.action(action),
+            };
+            return Some(event);
+        }
+
+        let event = match action {
+            Action::SelectAll => Event::CtrlChar('a'),
+            Action::Copy => Event::CtrlChar('c'),
+            Action::Paste => Event::CtrlChar('v'),
+            Action::Cut => Event::CtrlChar('x'),
+            Action::Undo => Event::CtrlChar('z'),
+            Action::Redo => Event::CtrlChar('y'),
+            Action::MoveLineUp => Event::Shift(Key::Up),
+            Action::MoveLineDown => Event::Shift(Key::Down),
+            Action::LineStart => Event::Shift(Key::Left),
+            Action::LineEnd => Event::Shift(Key::Right),
+            Action::PageUp => Event::Key(Key::PageUp),
+            Action::PageDown => Event::Key(Key::PageDown),
+            Action::FileStart => Event::Shift(Key::PageUp),
+            Action::FileEnd => Event::Shift(Key::PageDown),
+            Action::SelectUp => Event::CtrlShift(Key::Up),
+            Action::SelectDown => Event::CtrlShift(Key::Down),
+            Action::SelectLeft => Event::CtrlShift(Key::Left),
+            Action::SelectRight => Event::CtrlShift(Key::Right),
+            Action::Indent => Event::Key(Key::Tab),
+            Action::Dedent => Event::Shift(Key::Tab),
+            Action::AddCursorUp => Event::CtrlAlt(Key::Up),
+            Action::AddCursorDown => Event::CtrlAlt(Key::Down),
+            Action::SelectNext => Event::CtrlChar('d'),
+            // `ctrl+]` as reported by terminals
+            Action::JumpToBracket => Event::CtrlChar('5'),
+            Action::WordLeft => Event::Ctrl(Key::Left),
+            Action::WordRight => Event::Ctrl(Key::Right),
+            // `ctrl+backspace` as reported by terminals
+            Action::DeleteWordLeft => Event::CtrlChar('h'),
+            Action::DeleteWordRight => Event::Ctrl(Key::Del),
+            Action::ParagraphUp => Event::Alt(Key::Up),
+            Action::ParagraphDown => Event::Alt(Key::Down),
+            Action::Up
+            | Action::Down
+            | Action::Left
+            | Action::Right
+            | Action::DeleteChar
+            | Action::DeleteToLineEnd => return None,
+        };
+        Some(event)
+    }
+
+    /// The keybinding of `command` in this preset
+    pub fn command(self, command: &Command) -> Option<Event> {
+        if self == Preset::Emacs {
+            match command.id {
+                "quick_access" => return Some(Event::AltChar('x')),
+                "find" => return Some(Event::CtrlChar('s')),
+                "find_replace" => return Some(Event::AltChar('%')),
+                "close" => return Some(Event::CtrlChar('g')),
+                "goto" => return Some(Event::AltChar('g')),
+                _ => {}
+            }
+        }
+        command.keybinding.clone()
+    }
+}
+
+/// The keybindings of all commands and editor actions
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Keymap {
+    pub preset: Preset,
+    /// Changed keybindings of commands by their id, `None` if removed
+    commands: HashMap<String, Option<Event>>,
+    /// Changed keybindings of editor actions, `None` if removed
+    actions: HashMap<Action, Option<Event>>,
+}
+
+impl Keymap {
+    /// Parses the `keybindings` table of the config, changing those of `preset`
+    pub fn new(preset: Preset, keybindings: &BTreeMap<String, String>) -> Result<Self> {
+        let mut keymap = Keymap {
+            preset,
+            ..Default::default()
+        };
+        for (id, key) in keybindings {
+            let event = if key.is_empty() {
+                None
+            } else {
+                Some(parse_key(key).ok_or_else(|| {
+                    Error::Config(format!("\"{key}\" of \"{id}\" is not a valid key"))
+                })?)
+            };
+            if commands::get(id).is_some() {
+                keymap.commands.insert(id.clone(), event);
+            } else if let Some(action) = Action::ALL.iter().find(|action| action.id() == id) {
+                keymap.actions.insert(*action, event);
+            } else {
+                return Err(Error::Config(format!(
+                    "there is no command or editor action named \"{id}\""
+                )));
+            }
+        }
+        keymap.check_conflicts()?;
+        Ok(keymap)
+    }
+
+    /// Fails if a key is bound to more than one command or editor action, only one of them would run
+    fn check_conflicts(&self) -> Result<()> {
+        let commands = commands::COMMANDS
+            .iter()
+            .filter_map(|command| Some((command.id, self.command(command)?)));
+        let actions = Action::ALL
+            .iter()
+            .filter_map(|&action| Some((action.id(), self.action(action)?)));
+
+        let mut bound = HashMap::new();
+        for (id, event) in commands.chain(actions) {
+            if let Some(other) = bound.insert(event.clone(), id) {
+                return Err(Error::Config(format!(
+                    "\"{}\" is bound to both \"{other}\" and \"{id}\"",
+                    event_name(&event)
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The keybinding of `command`
+    pub fn command(&self, command: &Command) -> Option<Event> {
+        match self.commands.get(command.id) {
+            Some(event) => event.clone(),
+            None => self.preset.command(command),
+        }
+    }
+
+    /// The keybinding of `action`
+    pub fn action(&self, action: Action) -> Option<Event> {
+        match self.actions.get(&action) {
+            Some(event) => event.clone(),
+            None => self.preset.action(action),
+        }
+    }
+
+    /// The editor actions by their keybinding
+    pub fn editor(&self) -> HashMap<Event, Action> {
+        Action::ALL
+            .iter()
+            .filter_map(|&action| Some((self.action(action)?, action)))
+            .collect()
+    }
+}
+
+/// Parses a key like `ctrl+p`, `alt+shift+up` or `esc`
+pub fn parse_key(key: &str) -> Option<Event> {
+    let parts = key.split('+').map(str::trim).collect::<Vec<_>>();
+    let (name, modifiers) = parts.split_last()?;
+    let (mut ctrl, mut alt, mut shift) = (false, false, false);
+    for modifier in modifiers {
+        match modifier.to_lowercase().as_str() {
+            "ctrl" => ctrl = true,
+            "alt" => alt = true,
+            "shift" => shift = true,
+            _ => return None,
+        }
+    }
+
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match (ctrl, alt, shift) {
+            (false, false, false) => Some(Event::Char(c)),
+            (false, false, true) => Some(Event::Char(c.to_ascii_uppercase())),
+            (true, false, false) => Some(Event::CtrlChar(c.to_ascii_lowercase())),
+            (false, true, false) => Some(Event::AltChar(c)),
+            _ => None,
+        };
+    }
+
+    let key = match name.to_lowercase().as_str() {
+        "esc" | "escape" => Key::Esc,
+        "enter" | "return" => Key::Enter,
+        "tab" => Key::Tab,
+        "backspace" => Key::Backspace,
+        "del" | "delete" => Key::Del,
+        "ins" | "insert" => Key::Ins,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "pageup" | "page_up" => Key::PageUp,
+        "pagedown" | "page_down" => Key::PageDown,
+        name => {
+            let n = name.strip_prefix('f')?.parse::<u8>().ok()?;
+            Key::from_f((1..=12).contains(&n).then_some(n)?)
+        }
+    };
+    match (ctrl, alt, shift) {
+        (false, false, false) => Some(Event::Key(key)),
+        (false, false, true) => Some(Event::Shift(key)),
+        (false, true, false) => Some(Event::Alt(key)),
+        (false, true, true) => Some(Event::AltShift(key)),
+        (true, false, false) => Some(Event::Ctrl(key)),
+        (true, false, true) => Some(Event::CtrlShift(key)),
+        (true, true, false) => Some(Event::CtrlAlt(key)),
+        (true, true, true) => None,
+    }
+}
+
+/// Formats `event` like the keybindings in the info view
+pub fn event_name(event: &Event) -> String {
+    match event {
+        Event::Char(c) => c.to_string(),
+        Event::CtrlChar(c) => format!("Ctrl + {c}"),
+        Event::AltChar(c) => format!("Alt + {c}"),
+        Event::Key(key) => key_name(*key),
+        Event::Shift(key) => format!("Shift + {}", key_name(*key)),
+        Event::Alt(key) => format!("Alt + {}", key_name(*key)),
+        Event::AltShift(key) => format!("Alt + Shift + {}", key_name(*key)),
+        Event::Ctrl(key) => format!("Ctrl + {}", key_name(*key)),
+        Event::CtrlShift(key) => format!("Ctrl + Shift + {}", key_name(*key)),
+        Event::CtrlAlt(key) => format!("Ctrl + Alt + {}", key_name(*key)),
+        event => format!("{event:?}"),
+    }
+}
+
+fn key_name(key: Key) -> String {
+    match key {
+        Key::PageUp => "Page Up".to_string(),
+        Key::PageDown => "Page Down".to_string(),
+        Key::Backspace => "Backspace".to_string(),
+        Key::Del => "Delete".to_string(),
+        Key::Ins => "Insert".to_string(),
+        key => format!("{key:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(parse_key("x"), Some(Event::Char('x')));
+        assert_eq!(parse_key("shift+x"), Some(Event::Char('X')));
+        assert_eq!(parse_key("Ctrl+P"), Some(Event::CtrlChar('p')));
+        assert_eq!(parse_key("alt + g"), Some(Event::AltChar('g')));
+        assert_eq!(parse_key("esc"), Some(Event::Key(Key::Esc)));
+        assert_eq!(parse_key("alt+shift+up"), Some(Event::AltShift(Key::Up)));
+        assert_eq!(
+            parse_key("ctrl+page_down"),
+            Some(Event::Ctrl(Key::PageDown))
+        );
+        assert_eq!(parse_key("f12"), Some(Event::Key(Key::F12)));
+
+        assert_eq!(parse_key("f13"), None);
+        assert_eq!(parse_key("super+x"), None);
+        assert_eq!(parse_key("ctrl+alt+x"), None);
+        assert_eq!(parse_key("ctrl+alt+shift+up"), None);
+        assert_eq!(parse_key(""), None);
+    }
+
+    #[test]
+    fn event_names() {
+        assert_eq!(event_name(&Event::CtrlChar('p')), "Ctrl + p");
+        assert_eq!(event_name(&Event::CtrlShift(Key::Up)), "Ctrl + Shift + Up");
+        assert_eq!(event_name(&Event::Key(Key::PageDown)), "Page Down");
+        assert_eq!(event_name(&Event::Alt(Key::Del)), "Alt + Delete");
+    }
+
+    #[test]
+    fn change_keybindings() {
+        let keybindings = BTreeMap::from([
+            ("undo".to_string(), "f5".to_string()),
+            ("copy".to_string(), String::new()),
+        ]);
+        let keymap = Keymap::new(Preset::Default, &keybindings).unwrap();
+        assert_eq!(keymap.action(Action::Undo), Some(Event::Key(Key::F5)));
+        assert_eq!(keymap.action(Action::Copy), None);
+        assert_eq!(
+            keymap.action(Action::Paste),
+            Preset::Default.action(Action::Paste)
+        );
+
+        let unknown = BTreeMap::from([("nothing".to_string(), "x".to_string())]);
+        assert!(Keymap::new(Preset::Default, &unknown).is_err());
+        let invalid = BTreeMap::from([("undo".to_string(), "ctrl+".to_string())]);
+        assert!(Keymap::new(Preset::Default, &invalid).is_err());
+    }
+
+    #[test]
+    fn conflicting_keybindings() {
+        for preset in [Preset::Default, Preset::Emacs, Preset::Vim] {
+            assert!(Keymap::new(preset, &BTreeMap::new()).is_ok());
+        }
+
+        let conflict = BTreeMap::from([("undo".to_string(), "ctrl+v".to_string())]);
+        let Err(Error::Config(message)) = Keymap::new(Preset::Default, &conflict) else {
+            panic!("the conflict wasn't reported");
+        };
+        assert_eq!(
+            message,
+            "\"Ctrl + v\" is bound to both \"paste\" and \"undo\""
+        );
+        // moving the other keybinding away resolves it
+        let moved = BTreeMap::from([
+            ("undo".to_string(), "ctrl+v".to_string()),
+            ("paste".to_string(), "f5".to_string()),
+        ]);
+        assert!(Keymap::new(Preset::Default, &moved).is_ok());
+    }
+}
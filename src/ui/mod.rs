@@ -1,9 +1,16 @@
 //! Here are some general functions of updating the ui
 
+pub mod commands;
+pub mod diff;
 pub mod edit_area;
 pub mod file_tree;
+pub mod keymap;
 pub mod path_input;
+pub mod project_search;
 pub mod quick_access;
+pub mod search_bar;
+pub mod theme_picker;
+pub mod vim;
 
 use std::{
     fs, io,
@@ -15,8 +22,12 @@ use cursive_tree_view::TreeView;
 use file_tree::{load_parent, TreeEntry};
 
 use crate::{
-    app::{EditorPanel, FileData, State, TreePanel},
+    app::{DiskState, EditorPanel, FileData, State, TreePanel},
+    buffer::Buffer,
+    config,
     error::{Result, ResultExt},
+    indent::Indent,
+    syntax,
 };
 
 use self::edit_area::{Cursor, EditArea};
@@ -32,7 +43,8 @@ pub fn update_ui_state(
         open_file(siv, current_file).handle(siv);
     } else if project_path.exists() {
         siv.call_on_name("editor", |edit_area: &mut EditArea| {
-            edit_area.set_content(' ');
+            // a buffer of its own, the one of the last file is kept as it is
+            edit_area.set_buffer(Buffer::shared(" "));
             edit_area.set_cursor(Cursor::default());
             edit_area.set_scroll(Vec2::zero());
             edit_area.disable();
@@ -60,6 +72,11 @@ pub fn update_ui_state(
             load_parent(tree, project_path);
         });
 
+        let cb_sink = siv.cb_sink().clone();
+        state.index.rebuild(project_path, move || {
+            cb_sink.send(Box::new(quick_access::refresh)).ok();
+        });
+
         siv.set_user_data(state.open_new_project(project_path, current_file));
     } else {
         return Err(io::Error::new(
@@ -74,73 +91,158 @@ pub fn update_ui_state(
 
 /// Open a file, reading from fs if needed, updating title and edit_area content/highlighting, updating state, ...
 pub fn open_file(siv: &mut Cursive, file_to_open: &Path) -> Result<()> {
-    let mut state = siv
-        .with_user_data(|state: &mut State| state.clone())
-        .unwrap_or_default();
-    let file_to_open = file_to_open.canonicalize().unwrap_or_default();
-    let extension = file_to_open
-        .extension()
-        .unwrap_or_default()
-        .to_string_lossy();
-    if state.get_file(&file_to_open).is_none() {
-        let content = fs::read_to_string(file_to_open.clone())?;
+    // recovered files don't have to exist on disk
+    let file_to_open = file_to_open
+        .canonicalize()
+        .unwrap_or_else(|_| file_to_open.to_path_buf());
+    let opened = siv
+        .with_user_data(|state: &mut State| {
+            let file = state.get_file(&file_to_open)?;
+            Some((file.buffer.clone(), file.cursor, file.scroll_offset))
+        })
+        .flatten();
+    if let Some((buffer, cursor, scroll_offset)) = opened {
         siv.call_on_name("editor", |edit_area: &mut EditArea| {
-            edit_area.set_highlighting(&extension);
-            edit_area.set_content(content.clone());
-            edit_area.set_cursor(Cursor::default());
-            edit_area.set_scroll(Vec2::zero());
+            edit_area.set_buffer(buffer);
+            edit_area.set_cursor(cursor);
+            edit_area.set_scroll(scroll_offset);
             edit_area.enable();
         })
         .unwrap();
 
-        siv.set_user_data(state.open_new_file(
-            file_to_open.clone(),
-            FileData {
-                str: content,
-                ..Default::default()
-            },
-        ));
+        siv.with_user_data(|state: &mut State| state.current_file = Some(file_to_open.clone()));
     } else {
-        state = State {
-            current_file: Some(file_to_open.clone()),
-            ..state
+        let content = fs::read_to_string(file_to_open.clone())?;
+        let file_data = FileData {
+            disk: DiskState::new(&file_to_open, &content),
+            buffer: Buffer::shared(&content),
+            ..Default::default()
         };
-
         siv.call_on_name("editor", |edit_area: &mut EditArea| {
-            edit_area.set_highlighting(&extension);
-            edit_area.set_content(&state.get_current_file().unwrap().str);
-            edit_area.set_cursor(state.get_current_file().unwrap().cursor);
-            edit_area.set_scroll(state.get_current_file().unwrap().scroll_offset);
+            edit_area.set_buffer(file_data.buffer.clone());
+            edit_area.set_cursor(Cursor::default());
+            edit_area.set_scroll(Vec2::zero());
             edit_area.enable();
         })
         .unwrap();
 
-        siv.set_user_data(state.clone());
+        siv.with_user_data(|state: &mut State| {
+            state.open_new_file(file_to_open.clone(), file_data);
+        });
     }
 
+    let edited = siv
+        .with_user_data(|state: &mut State| {
+            state.add_recent_file(file_to_open.clone());
+            state.is_file_edited(&file_to_open)
+        })
+        .unwrap_or_default();
+    update_language(siv);
+    update_indent(siv);
+    update_wrap(siv);
+
     // check if file has been added && update title accordingly
-    update_title(siv, Some(&state), &file_to_open);
+    update_title(siv, edited, &file_to_open);
 
     Ok(())
 }
 
+/// Highlights the current file in its chosen or detected language
+pub fn update_language(siv: &mut Cursive) {
+    let Some((path, language, first_line)) = siv
+        .with_user_data(|state: &mut State| {
+            let path = state.current_file.clone()?;
+            let file = state.get_file(&path)?;
+            let first_line = file.content().line(0).to_string();
+            let first_line = first_line.trim_end_matches(['\n', '\r']).to_string();
+            Some((path, file.language.clone(), first_line))
+        })
+        .flatten()
+    else {
+        return;
+    };
+
+    let syntaxes = syntax::syntaxes();
+    let config = config::get();
+    let synref = language
+        .and_then(|name| syntax::find(&syntaxes, &name))
+        .unwrap_or_else(|| syntax::detect(&syntaxes, &config.languages, &path, &first_line))
+        .clone();
+    siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        edit_area.set_syntax(syntaxes.clone(), &synref);
+    });
+}
+
+/// Indents the current file in its chosen or detected indentation, new files as configured
+pub fn update_indent(siv: &mut Cursive) {
+    let Some(indent) = siv
+        .with_user_data(|state: &mut State| {
+            let path = state.current_file.clone()?;
+            Some(state.get_file(&path)?.indent)
+        })
+        .flatten()
+    else {
+        return;
+    };
+
+    siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        let indent = indent
+            .or_else(|| Indent::detect(edit_area.get_content()))
+            .unwrap_or_else(|| config::get().editor.indent());
+        edit_area.set_indent(indent);
+    });
+}
+
+/// Wraps the long lines of the current file if it's toggled on
+pub fn update_wrap(siv: &mut Cursive) {
+    let Some(wrap) = siv
+        .with_user_data(|state: &mut State| {
+            let path = state.current_file.clone()?;
+            Some(state.get_file(&path)?.wrap)
+        })
+        .flatten()
+    else {
+        return;
+    };
+
+    if let Some(cb) = siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        edit_area.set_soft_wrap(wrap)
+    }) {
+        cb(siv);
+    }
+}
+
+/// Updates the title of the editor panel for the current file
+pub fn update_current_title(siv: &mut Cursive) {
+    let Some((current_file, edited)) = siv
+        .with_user_data(|state: &mut State| {
+            let current_file = state.current_file.clone()?;
+            let edited = state.is_file_edited(&current_file);
+            Some((current_file, edited))
+        })
+        .flatten()
+    else {
+        return;
+    };
+    update_title(siv, edited, &current_file);
+}
+
 /// Update the title of the editor panel including the current editing state via adding `*`
-pub fn update_title(siv: &mut Cursive, state: Option<&State>, path: &Path) {
+pub fn update_title(siv: &mut Cursive, edited: bool, path: &Path) {
     let file_name = path
         .file_name()
         .unwrap_or_default()
         .to_string_lossy()
         .to_string();
 
-    let title = if let Some(state) = state {
-        if state.is_file_edited(&path.to_path_buf()) {
-            file_name + " *"
-        } else {
-            file_name
-        }
-    } else {
-        file_name
-    };
+    let mut title = if edited { file_name + " *" } else { file_name };
+    // the mode of the modal editing
+    if let Some(mode) = siv
+        .call_on_name("editor", |edit_area: &mut EditArea| edit_area.mode())
+        .flatten()
+    {
+        title = format!("{title} [{}]", mode.name());
+    }
 
     siv.call_on_name("editor_title", |view: &mut EditorPanel| {
         view.set_title(title);
@@ -0,0 +1,558 @@
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use cursive::{
+    event::{Event, Key},
+    theme::{BaseColor, Color, Effect, Style},
+    utils::markup::StyledString,
+    view::{Nameable, Resizable, Scrollable},
+    views::{
+        Button, Checkbox, EditView, HideableView, LinearLayout, NamedView, OnEventView, SelectView,
+        TextView,
+    },
+    Cursive,
+};
+
+use crate::{
+    app::State,
+    error::{Error, ResultExt},
+    grep::{self, FileMatches, LineReplacement},
+    search::{Search, SearchOptions},
+    swap,
+};
+
+use super::{edit_area::EditArea, open_file, search_bar::is_checked};
+
+pub type ProjectSearch = HideableView<OnEventView<LinearLayout>>;
+
+/// Height of the result list.
+const RESULTS_HEIGHT: usize = 12;
+
+/// Max amount of characters of a line preview.
+const PREVIEW_LEN: usize = 200;
+
+/// Incremented for every search, so results of outdated ones are dropped
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// The search of the listed results when replacing, their replacements are updated with it
+static REPLACING: Mutex<Option<Search>> = Mutex::new(None);
+
+/// A position in a file of the result list
+#[derive(Clone, Debug)]
+pub struct Hit {
+    pub path: PathBuf,
+    /// Line index, starting at 0
+    pub line: usize,
+    /// Character column, starting at 0
+    pub column: usize,
+    /// If this is the header of a file, not a match
+    pub header: bool,
+    /// The replacement of the match, only when replacing
+    pub replace: Option<LineReplacement>,
+    /// If the replacement should be applied
+    pub ticked: bool,
+}
+
+/// Creates the (hidden) panel for searching in all files of the project, docked below the editor
+///
+/// `Enter` in the query starts the search, the results are streamed into the list below, grouped
+/// by file. Submitting a result opens its file at the match, the panel stays open.
+/// `Alt` + `c`, `Alt` + `w` and `Alt` + `r` toggle case sensitivity, whole word and regex matching.
+///
+/// The replace row is only shown if opened via [`open_replace`]. Then every match is listed with
+/// a preview of its replacement, `Space` ticks or unticks it (or all of a file on its header) and
+/// `Alt` + `a` applies the ticked ones.
+pub fn new() -> NamedView<ProjectSearch> {
+    let search_row = LinearLayout::horizontal()
+        .child(TextView::new("Search  "))
+        .child(
+            EditView::new()
+                .on_submit(|siv, _| search(siv))
+                .with_name("project_search_query")
+                .full_width(),
+        )
+        .child(
+            TextView::new("")
+                .with_name("project_search_status")
+                .fixed_width(20),
+        )
+        .child(
+            Checkbox::labelled("Case")
+                .on_change(|siv, _| search(siv))
+                .with_name("project_search_case"),
+        )
+        .child(TextView::new(" "))
+        .child(
+            Checkbox::labelled("Word")
+                .on_change(|siv, _| search(siv))
+                .with_name("project_search_word"),
+        )
+        .child(TextView::new(" "))
+        .child(
+            Checkbox::labelled("Regex")
+                .on_change(|siv, _| search(siv))
+                .with_name("project_search_regex"),
+        );
+
+    let replace_row = LinearLayout::horizontal()
+        .child(TextView::new("Replace "))
+        .child(
+            EditView::new()
+                .on_edit(|siv, _, _| update_replacements(siv))
+                .with_name("project_replace_query")
+                .full_width(),
+        )
+        .child(Button::new_raw("[Apply]", apply));
+
+    let results = OnEventView::new(
+        SelectView::<Hit>::new()
+            .on_submit(open_hit)
+            .with_name("project_search_results"),
+    )
+    .on_event(' ', toggle_hit)
+    .scrollable()
+    .fixed_height(RESULTS_HEIGHT);
+
+    let layout = OnEventView::new(
+        LinearLayout::vertical()
+            .child(search_row)
+            .child(HideableView::new(replace_row).with_name("project_replace_row"))
+            .child(results),
+    )
+    .on_pre_event(Key::Esc, close)
+    .on_event(Event::AltChar('a'), apply)
+    .on_event(Event::AltChar('c'), |siv| {
+        toggle(siv, "project_search_case");
+    })
+    .on_event(Event::AltChar('w'), |siv| {
+        toggle(siv, "project_search_word");
+    })
+    .on_event(Event::AltChar('r'), |siv| {
+        toggle(siv, "project_search_regex");
+    });
+
+    HideableView::new(layout)
+        .hidden()
+        .with_name("project_search")
+}
+
+/// Shows the panel and focuses its query
+pub fn open(siv: &mut Cursive) {
+    show(siv, false);
+    siv.focus_name("project_search_query").unwrap();
+}
+
+/// Shows the panel including the replace row
+///
+/// The replacement is focused if there is already a query, otherwise the query.
+pub fn open_replace(siv: &mut Cursive) {
+    show(siv, true);
+    let query = siv
+        .call_on_name("project_search_query", |view: &mut EditView| {
+            view.get_content()
+        })
+        .unwrap();
+    if query.is_empty() {
+        siv.focus_name("project_search_query").unwrap();
+    } else {
+        siv.focus_name("project_replace_query").unwrap();
+    }
+}
+
+/// Shows the panel, searching again if the replace row was toggled
+fn show(siv: &mut Cursive, replace: bool) {
+    siv.call_on_name("project_search", |view: &mut ProjectSearch| view.unhide());
+    let toggled = siv
+        .call_on_name(
+            "project_replace_row",
+            |view: &mut HideableView<LinearLayout>| {
+                let toggled = view.is_visible() != replace;
+                view.set_visible(replace);
+                toggled
+            },
+        )
+        .unwrap_or_default();
+    if toggled {
+        search(siv);
+    }
+}
+
+fn is_replacing(siv: &mut Cursive) -> bool {
+    siv.call_on_name(
+        "project_replace_row",
+        |view: &mut HideableView<LinearLayout>| view.is_visible(),
+    )
+    .unwrap_or_default()
+}
+
+/// Hides the panel, cancelling a running search
+pub fn close(siv: &mut Cursive) {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    siv.call_on_name("project_search", |view: &mut ProjectSearch| view.hide());
+    siv.focus_name("editor").ok();
+}
+
+/// Starts searching for the current query, replacing the previous results
+fn search(siv: &mut Cursive) {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+
+    siv.call_on_name("project_search_results", |view: &mut SelectView<Hit>| {
+        view.clear();
+    });
+    *REPLACING.lock().unwrap() = None;
+
+    let query = siv
+        .call_on_name("project_search_query", |view: &mut EditView| {
+            view.get_content()
+        })
+        .unwrap();
+    let options = SearchOptions {
+        case_sensitive: is_checked(siv, "project_search_case"),
+        whole_word: is_checked(siv, "project_search_word"),
+        regex: is_checked(siv, "project_search_regex"),
+    };
+    let search = match Search::new(&query, options) {
+        Ok(Some(search)) => search,
+        Ok(None) => return set_status(siv, ""),
+        Err(_) => return set_status(siv, " Invalid regex"),
+    };
+    set_status(siv, " Searching...");
+
+    let replace = is_replacing(siv).then(|| search.clone());
+    REPLACING.lock().unwrap().clone_from(&replace);
+
+    let state = siv
+        .with_user_data(|state: &mut State| state.clone())
+        .unwrap_or_default();
+    let buffers = state
+        .files
+        .iter()
+        .filter(|(path, _)| state.is_file_edited(path))
+        .map(|(path, file)| (path.clone(), file.content().to_string()))
+        .collect();
+
+    let project_path = state.project_path.clone();
+    let file_sink = siv.cb_sink().clone();
+    let done_sink = siv.cb_sink().clone();
+    grep::spawn(
+        state.project_path,
+        search,
+        buffers,
+        move || GENERATION.load(Ordering::SeqCst) != generation,
+        move |matches| {
+            let project_path = project_path.clone();
+            let replace = replace.clone();
+            file_sink
+                .send(Box::new(move |siv| {
+                    if GENERATION.load(Ordering::SeqCst) == generation {
+                        // the replacement may have been edited while searching
+                        let replacement = replacement(siv);
+                        let replace = replace
+                            .as_ref()
+                            .map(|search| (search, replacement.as_str()));
+                        add_results(siv, &project_path, matches, replace);
+                    }
+                }))
+                .ok();
+        },
+        move || {
+            done_sink
+                .send(Box::new(move |siv| {
+                    if GENERATION.load(Ordering::SeqCst) == generation {
+                        update_status(siv);
+                    }
+                }))
+                .ok();
+        },
+    );
+}
+
+fn replacement(siv: &mut Cursive) -> String {
+    siv.call_on_name("project_replace_query", |view: &mut EditView| {
+        view.get_content().to_string()
+    })
+    .unwrap_or_default()
+}
+
+/// Updates the replacements of the listed matches to the edited replacement, keeping their ticks
+fn update_replacements(siv: &mut Cursive) {
+    let Some(search) = REPLACING.lock().unwrap().clone() else {
+        return;
+    };
+    let replacement = replacement(siv);
+    siv.call_on_name("project_search_results", |view: &mut SelectView<Hit>| {
+        for i in 0..view.len() {
+            let Some((label, hit)) = view.get_item_mut(i) else {
+                continue;
+            };
+            let Some(replace) = &mut hit.replace else {
+                continue;
+            };
+            if let Some(replaced) =
+                search.replacement_at(&replace.text, replace.range.start, &replacement)
+            {
+                replace.replacement = replaced;
+                *label = replace_preview(hit);
+            }
+        }
+    });
+}
+
+/// Appends the matches of a file to the result list
+///
+/// If `replace` is given, every match is listed separately with a preview of its replacement.
+fn add_results(
+    siv: &mut Cursive,
+    project_path: &Path,
+    matches: FileMatches,
+    replace: Option<(&Search, &str)>,
+) {
+    let relative = matches
+        .path
+        .strip_prefix(project_path)
+        .unwrap_or(&matches.path)
+        .to_string_lossy()
+        .to_string();
+
+    let mut hits = Vec::new();
+    for line in &matches.lines {
+        if let Some((search, replacement)) = replace {
+            for (range, replacement) in search.replacements_in(&line.text, replacement) {
+                let hit = Hit {
+                    path: matches.path.clone(),
+                    line: line.line,
+                    column: line.text[..range.start].chars().count(),
+                    header: false,
+                    replace: Some(LineReplacement {
+                        line: line.line,
+                        text: line.text.clone(),
+                        range,
+                        replacement,
+                    }),
+                    ticked: true,
+                };
+                hits.push((replace_preview(&hit), hit));
+            }
+        } else {
+            let hit = Hit {
+                path: matches.path.clone(),
+                line: line.line,
+                column: line.text[..line.ranges[0].start].chars().count(),
+                header: false,
+                replace: None,
+                ticked: false,
+            };
+            hits.push((preview(line.line, &line.text, &line.ranges), hit));
+        }
+    }
+
+    siv.call_on_name("project_search_results", |view: &mut SelectView<Hit>| {
+        let mut header = StyledString::styled(relative, Effect::Bold);
+        header.append_plain(format!(" ({})", hits.len()));
+        view.add_item(
+            header,
+            Hit {
+                path: matches.path.clone(),
+                line: 0,
+                column: 0,
+                header: true,
+                replace: None,
+                ticked: false,
+            },
+        );
+        view.add_all(hits);
+    });
+}
+
+/// The line number and the line without indentation, the matches emphasized
+fn preview(line: usize, text: &str, ranges: &[Range<usize>]) -> StyledString {
+    let style = Style::from(Effect::Bold).combine(Effect::Underline);
+    let indentation = text.len() - text.trim_start().len();
+
+    let mut styled = StyledString::plain(format!("{:>6}  ", line + 1));
+    for (i, (offset, c)) in text
+        .char_indices()
+        .skip_while(|(i, _)| *i < indentation)
+        .enumerate()
+    {
+        if i >= PREVIEW_LEN {
+            styled.append_plain("…");
+            break;
+        }
+        if ranges.iter().any(|range| range.contains(&offset)) {
+            styled.append_styled(c.to_string(), style);
+        } else {
+            styled.append_plain(c.to_string());
+        }
+    }
+    styled
+}
+
+/// The tick box, the line number and the line with the match replaced
+fn replace_preview(hit: &Hit) -> StyledString {
+    let Some(replace) = &hit.replace else {
+        return StyledString::new();
+    };
+    let text = &replace.text;
+    let start = text.len() - text.trim_start().len();
+    let start = start.min(replace.range.start);
+
+    let tick = if hit.ticked { "[x]" } else { "[ ]" };
+    let mut styled = StyledString::plain(format!("{tick}{:>6}  ", hit.line + 1));
+    styled.append_plain(&text[start..replace.range.start]);
+    styled.append_styled(
+        &text[replace.range.clone()],
+        Style::from(Color::Dark(BaseColor::Red)).combine(Effect::Strikethrough),
+    );
+    styled.append_styled(&replace.replacement, Color::Dark(BaseColor::Green));
+    styled.append_plain(&text[replace.range.end..]);
+    styled
+}
+
+/// Ticks or unticks the selected match, or all matches of the file if its header is selected
+fn toggle_hit(siv: &mut Cursive) {
+    siv.call_on_name("project_search_results", |view: &mut SelectView<Hit>| {
+        let Some(selected) = view.selected_id() else {
+            return;
+        };
+        let Some((_, hit)) = view.get_item(selected) else {
+            return;
+        };
+        let file_hits = if hit.header {
+            let end = (selected + 1..view.len())
+                .find(|&i| view.get_item(i).is_some_and(|(_, hit)| hit.header))
+                .unwrap_or(view.len());
+            selected + 1..end
+        } else {
+            selected..selected + 1
+        };
+        let ticked = !file_hits
+            .clone()
+            .all(|i| view.get_item(i).is_some_and(|(_, hit)| hit.ticked));
+
+        for i in file_hits {
+            if let Some((label, hit)) = view.get_item_mut(i) {
+                if hit.replace.is_some() {
+                    hit.ticked = ticked;
+                    *label = replace_preview(hit);
+                }
+            }
+        }
+    });
+}
+
+/// Applies the ticked replacements
+///
+/// Open buffers are updated in memory and marked as edited, other files are written atomically.
+fn apply(siv: &mut Cursive) {
+    if !is_replacing(siv) {
+        return;
+    }
+    let mut files: BTreeMap<PathBuf, Vec<LineReplacement>> = BTreeMap::new();
+    siv.call_on_name("project_search_results", |view: &mut SelectView<Hit>| {
+        for (_, hit) in view.iter() {
+            if let (true, Some(replace)) = (hit.ticked, &hit.replace) {
+                files
+                    .entry(hit.path.clone())
+                    .or_default()
+                    .push(replace.clone());
+            }
+        }
+    });
+
+    let state = siv
+        .with_user_data(|state: &mut State| state.clone())
+        .unwrap_or_default();
+
+    let mut replaced = 0;
+    let mut replaced_files = 0;
+    for (path, replacements) in files {
+        let content = match state.get_file(&path) {
+            Some(file) => file.content().to_string(),
+            None => match grep::read_text(&path) {
+                Some(content) => content,
+                None => continue,
+            },
+        };
+        let (content, count) = grep::replace_lines(&content, &replacements);
+        if count == 0 {
+            continue;
+        }
+
+        if state.get_file(&path).is_some() {
+            siv.with_user_data(|state: &mut State| {
+                if let Some(file) = state.files.get_mut(&path) {
+                    file.set_content(content);
+                    swap::update(&path, file.content());
+                }
+                state.files_edited.insert(path.clone(), true);
+            });
+            if state.current_file.as_ref() == Some(&path) {
+                open_file(siv, &path).handle(siv);
+            }
+        } else if let Err(e) = grep::write_atomic(&path, &content) {
+            Error::from(e).to_dialog(siv);
+            continue;
+        }
+        replaced += count;
+        replaced_files += 1;
+    }
+
+    siv.call_on_name("project_search_results", |view: &mut SelectView<Hit>| {
+        view.clear();
+    });
+    let unit = if replaced_files == 1 { "file" } else { "files" };
+    set_status(
+        siv,
+        format!(" Replaced {replaced} in {replaced_files} {unit}"),
+    );
+}
+
+/// Shows the amount of results after the search is done
+fn update_status(siv: &mut Cursive) {
+    let (lines, files) = siv
+        .call_on_name("project_search_results", |view: &mut SelectView<Hit>| {
+            let files = view.iter().filter(|(_, hit)| hit.header).count();
+            (view.len(), files)
+        })
+        .unwrap_or_default();
+    let status = if lines == 0 {
+        " No results".to_string()
+    } else {
+        let unit = if files == 1 { "file" } else { "files" };
+        format!(" {} in {files} {unit}", lines - files)
+    };
+    set_status(siv, status);
+}
+
+fn set_status(siv: &mut Cursive, status: impl Into<StyledString>) {
+    siv.call_on_name("project_search_status", |view: &mut TextView| {
+        view.set_content(status);
+    });
+}
+
+/// Opens the file of `hit` with the cursor placed at the match
+fn open_hit(siv: &mut Cursive, hit: &Hit) {
+    if let Err(e) = open_file(siv, &hit.path) {
+        e.to_dialog(siv);
+        return;
+    }
+    if let Some(cb) = siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        edit_area.goto(hit.line, hit.column)
+    }) {
+        cb(siv);
+    }
+    siv.focus_name("editor").ok();
+}
+
+fn toggle(siv: &mut Cursive, name: &str) {
+    siv.call_on_name(name, |checkbox: &mut Checkbox| checkbox.toggle());
+    search(siv);
+}
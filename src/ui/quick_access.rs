@@ -5,10 +5,13 @@ use std::{
 };
 
 use cursive::{
+    event::Key,
+    theme::{Effect, Style},
+    utils::markup::StyledString,
     view::{Nameable, Resizable, Scrollable},
     views::{
-        DebugView, Dialog, EditView, LinearLayout, ListView, NamedView, ScrollView, SelectView,
-        TextView,
+        DebugView, Dialog, EditView, LinearLayout, ListView, NamedView, OnEventView, ScrollView,
+        SelectView, TextView,
     },
     Cursive,
 };
@@ -16,21 +19,25 @@ use cursive_tree_view::TreeView;
 
 use crate::{
     app::{
-        FileData, State, PKG_AUTHORS, PKG_DESCRIPTION, PKG_LICENSE, PKG_NAME, PKG_REPOSITORY,
-        PKG_VERSION,
+        content_hash, DiskState, State, PKG_AUTHORS, PKG_DESCRIPTION, PKG_LICENSE, PKG_NAME,
+        PKG_REPOSITORY, PKG_VERSION,
     },
+    config,
     error::{Error, Result, ResultExt},
+    file_index, session, swap,
     ui::{
+        commands::{self, Command, COMMANDS},
+        edit_area::EditArea,
         file_tree::{load_parent, TreeEntry},
+        keymap::{event_name, Action},
         open_file, path_input,
     },
 };
 
 use super::{update_title, update_ui_state};
 
-const VARIANTS: &[&str] = &[
-    "info", "debug", "open", "save", "new", "delete", "rename", "quit",
-];
+/// Max amount of files shown in the Quick Access view.
+const MAX_RESULTS: usize = 100;
 
 struct Entry {
     str: String,
@@ -38,60 +45,75 @@ struct Entry {
 }
 
 impl Entry {
-    fn file(str: String) -> Self {
+    fn file(str: String, position: Option<(usize, usize)>) -> Self {
         Self {
             str,
-            ty: EntryType::File,
+            ty: EntryType::File(position),
         }
     }
-    fn command(str: String) -> Self {
+    fn command(str: String, args: Vec<String>) -> Self {
         Self {
             str,
-            ty: EntryType::Command,
+            ty: EntryType::Command(args),
+        }
+    }
+    fn completion(str: String) -> Self {
+        Self {
+            str,
+            ty: EntryType::Completion,
         }
     }
 }
 
 enum EntryType {
-    File,
-    Command,
+    /// A file, opened at the optional line and column (starting at 1)
+    File(Option<(usize, usize)>),
+    /// A command, run with the given arguments
+    Command(Vec<String>),
+    /// A completed argument, `str` is the completed query
+    Completion,
 }
 
 /// Creates a new Quick Access view
 ///
-/// The recent file are visible and can be filtered via the `EditView`.
-/// Typing in a `>` shows you all current commands which are `info`, `open`, `new`, `rename`, and `delete`.
+/// The files of the project are fuzzy searched via the `EditView`, recently opened ones are ranked higher.
+/// Appending `:line` or `:line:column` to the query opens the file at that position.
+/// Typing in a `>` shows you all commands of the registry together with their keybindings.
+/// Arguments typed in after a command are completed, `Tab` inserts the selected completion.
 ///
 /// Pressing enter in the `EditView` will auto select the current selected option.
 pub fn new(siv: &mut Cursive) -> Result<()> {
     if let Some(pos) = siv.screen_mut().find_layer_from_name("quick_access_view") {
         siv.screen_mut().remove_layer(pos);
     } else {
-        let state = siv
-            .with_user_data(|state: &mut State| state.clone())
+        let matches = siv
+            .with_user_data(|state: &mut State| search_fn(state, ""))
             .unwrap();
+
         siv.add_layer(
             Dialog::new()
                 .padding_lrtb(1, 1, 1, 0)
                 .content(
                     LinearLayout::vertical()
                         .child(
-                            EditView::new()
-                                .on_edit(on_edit)
-                                .on_submit(on_submit)
-                                .with_name("query"),
+                            OnEventView::new(
+                                EditView::new()
+                                    .on_edit(on_edit)
+                                    .on_submit(on_submit)
+                                    .with_name("query"),
+                            )
+                            .on_pre_event(Key::Tab, complete),
                         )
                         .child(
                             SelectView::new()
-                                .with_all(
-                                    search_fn(&state, "")
-                                        .into_iter()
-                                        .map(|f| (f.str.clone(), f)),
-                                )
+                                .with_all(matches)
                                 .on_submit(show_next_window)
+                                .on_select(show_description)
                                 .with_name("matches")
-                                .scrollable(),
+                                .scrollable()
+                                .full_height(),
                         )
+                        .child(TextView::new("").with_name("description"))
                         .fixed_height(10),
                 )
                 .dismiss_button("Cancel")
@@ -103,50 +125,223 @@ pub fn new(siv: &mut Cursive) -> Result<()> {
     Ok(())
 }
 
+/// Opens the Quick Access view with `query` already typed in
+pub fn open_with(siv: &mut Cursive, query: &str) -> Result<()> {
+    if siv
+        .screen_mut()
+        .find_layer_from_name("quick_access_view")
+        .is_none()
+    {
+        new(siv)?;
+    }
+    set_query(siv, query);
+    Ok(())
+}
+
+/// Replaces the query, updating the matches
+fn set_query(siv: &mut Cursive, query: &str) {
+    if let Some(cb) = siv.call_on_name("query", |view: &mut EditView| view.set_content(query)) {
+        cb(siv);
+    }
+    siv.focus_name("query").ok();
+}
+
+/// Inserts the selected completion, or the first one if a command is selected
+fn complete(siv: &mut Cursive) {
+    let completion = siv
+        .call_on_name("matches", |view: &mut SelectView<Entry>| {
+            let selected = view.selection()?;
+            if let EntryType::Completion = selected.ty {
+                return Some(selected.str.clone());
+            }
+            view.iter()
+                .find(|(_, entry)| matches!(entry.ty, EntryType::Completion))
+                .map(|(_, entry)| entry.str.clone())
+        })
+        .flatten();
+    if let Some(completion) = completion {
+        set_query(siv, &completion);
+    }
+}
+
+/// Updates the matches of an open Quick Access view, e.g. after the index was rebuilt
+pub fn refresh(siv: &mut Cursive) {
+    if let Some(query) = siv.call_on_name("query", |view: &mut EditView| view.get_content()) {
+        on_edit(siv, &query, 0);
+    }
+}
+
 fn on_edit(siv: &mut Cursive, query: &str, _cursor: usize) {
-    let state = siv
-        .with_user_data(|state: &mut State| state.clone())
+    let matches = siv
+        .with_user_data(|state: &mut State| search_fn(state, query))
         .unwrap();
-    let matches = search_fn(&state, query);
     // Update the `matches` view with the filtered array of cities
-    siv.call_on_name("matches", |v: &mut SelectView<Entry>| {
+    let selection = siv.call_on_name("matches", |v: &mut SelectView<Entry>| {
         v.clear();
-        v.add_all(matches.into_iter().map(|f| (f.str.clone(), f)));
+        v.add_all(matches);
+        v.selection()
     });
+    if let Some(entry) = selection.flatten() {
+        show_description(siv, &entry);
+    } else {
+        siv.call_on_name("description", |view: &mut TextView| view.set_content(""));
+    }
 }
 
-fn search_fn(state: &State, query: &'_ str) -> Vec<Entry> {
+/// Shows the description of a selected command below the matches
+fn show_description(siv: &mut Cursive, entry: &Entry) {
+    let description = match entry.ty {
+        EntryType::Command(_) => {
+            commands::get(&entry.str).map_or("", |command| command.description)
+        }
+        EntryType::File(_) | EntryType::Completion => "",
+    };
+    siv.call_on_name("description", |view: &mut TextView| {
+        view.set_content(description);
+    });
+}
+
+fn search_fn(state: &State, query: &'_ str) -> Vec<(StyledString, Entry)> {
     if query.chars().next().unwrap_or_default() == '>' {
-        let query = query.get(1..).unwrap_or("");
-        VARIANTS
+        let args = commands::split_args(query.get(1..).unwrap_or(""));
+        if let (Some(command), true) = (
+            args.first().and_then(|id| commands::get(id)),
+            args.len() > 1,
+        ) {
+            return command_args(state, command, &args);
+        }
+
+        let query = args.first().cloned().unwrap_or_default().to_lowercase();
+        let id_width = COMMANDS.iter().map(|c| c.id.len()).max().unwrap_or(0);
+        let title_width = COMMANDS.iter().map(|c| c.title.len()).max().unwrap_or(0);
+
+        // commands starting with the query first, then the ones containing it
+        let mut commands = COMMANDS
             .iter()
-            .copied()
-            .filter(|&item| {
-                let item = item.to_lowercase();
-                let query = query.to_lowercase();
-                item.contains(&query)
+            .filter_map(|command| {
+                let rank = if command.id.starts_with(&query) {
+                    0
+                } else if command.id.contains(&query) {
+                    1
+                } else if command.title.to_lowercase().contains(&query) {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, command))
+            })
+            .collect::<Vec<_>>();
+        commands.sort_by_key(|(rank, _)| *rank);
+
+        commands
+            .into_iter()
+            .map(|(_, command)| {
+                let mut label = StyledString::plain(format!(
+                    "{:id_width$}  {:title_width$}",
+                    command.id, command.title
+                ));
+                if let Some(shortcut) = command.shortcut() {
+                    label.append_styled(format!("  {shortcut}"), Effect::Dim);
+                }
+                (label, Entry::command(command.id.to_string(), Vec::new()))
             })
-            .map(|f| Entry::command(f.to_string()))
             .collect()
     } else {
-        let mut filtered = state
-            .files
+        let (query, position) = split_position(query);
+
+        // opened files could be ignored, but should still be found
+        let index = state.index.files();
+        let mut files = state
+            .recent_files
             .iter()
-            .filter(|p| {
-                p.0.starts_with(&state.project_path) && {
-                    let item = p.0.to_string_lossy().to_lowercase();
-                    let query = query.to_lowercase();
-                    item.contains(&query)
-                }
-            })
+            .filter(|path| index.binary_search(path).is_err() && path.is_file())
+            .cloned()
             .collect::<Vec<_>>();
-        filtered.sort_by(|a, b| b.0.cmp(a.0));
+        files.extend(index.iter().cloned());
+
+        file_index::find(
+            &files,
+            &state.project_path,
+            query,
+            &state.recent_files,
+            MAX_RESULTS,
+        )
+        .into_iter()
+        .map(|file| {
+            let relative = file
+                .path
+                .strip_prefix(&state.project_path)
+                .unwrap_or(&file.path)
+                .to_string_lossy()
+                .to_string();
+            (
+                highlight(&relative, &file.indices),
+                Entry::file(file.path.to_string_lossy().to_string(), position),
+            )
+        })
+        .collect()
+    }
+}
 
-        filtered
-            .iter()
-            .map(|f| Entry::file(f.0.to_string_lossy().to_string()))
-            .collect()
+/// The `command` with the arguments typed in so far, followed by the completions of the last one
+///
+/// `args` starts with the id of the command.
+fn command_args(state: &State, command: &Command, args: &[String]) -> Vec<(StyledString, Entry)> {
+    let mut label = StyledString::plain(command.title);
+    label.append_styled(format!("  {}", command.usage()), Effect::Dim);
+    if let Some(shortcut) = command.shortcut() {
+        label.append_styled(format!("  {shortcut}"), Effect::Dim);
+    }
+    let run_args = args[1..]
+        .iter()
+        .filter(|arg| !arg.is_empty())
+        .cloned()
+        .collect();
+    let mut entries = vec![(label, Entry::command(command.id.to_string(), run_args))];
+
+    let (value, previous) = args.split_last().unwrap();
+    if let Some(arg) = command.args.get(args.len() - 2) {
+        for completion in commands::complete(arg.kind, value, &state.project_path) {
+            let mut completed = previous.to_vec();
+            completed.push(completion.clone());
+            let query = format!(">{}", commands::join_args(&completed));
+            entries.push((completion.into(), Entry::completion(query)));
+        }
+    }
+    entries
+}
+
+/// Splits a trailing `:line` or `:line:column` off the `query`
+fn split_position(query: &str) -> (&str, Option<(usize, usize)>) {
+    let Some((rest, last)) = query.rsplit_once(':') else {
+        return (query, None);
+    };
+    if last.is_empty() {
+        return (rest, None);
+    }
+    let Ok(last) = last.parse() else {
+        return (query, None);
+    };
+    if let Some((file, line)) = rest.rsplit_once(':') {
+        if let Ok(line) = line.parse() {
+            return (file, Some((line, last)));
+        }
     }
+    (rest, Some((last, 1)))
+}
+
+/// Emphasizes the characters at `indices` of `text`
+fn highlight(text: &str, indices: &[usize]) -> StyledString {
+    let style = Style::from(Effect::Bold).combine(Effect::Underline);
+    let mut styled = StyledString::new();
+    for (i, c) in text.chars().enumerate() {
+        if indices.contains(&i) {
+            styled.append_styled(c.to_string(), style);
+        } else {
+            styled.append_plain(c.to_string());
+        }
+    }
+    styled
 }
 
 fn on_submit(siv: &mut Cursive, _: &str) {
@@ -159,91 +354,76 @@ fn on_submit(siv: &mut Cursive, _: &str) {
 
 fn show_next_window(siv: &mut Cursive, entry: &Entry) {
     match entry.ty {
-        EntryType::File => {
+        EntryType::File(position) => {
             let goto_file = &PathBuf::from(entry.str.clone());
             if let Err(e) = open_file(siv, goto_file) {
                 Into::<Error>::into(e).to_dialog(siv);
                 return;
             }
+            if let Some((line, column)) = position {
+                if let Some(cb) = siv.call_on_name("editor", |edit_area: &mut EditArea| {
+                    edit_area.goto(line.saturating_sub(1), column.saturating_sub(1))
+                }) {
+                    cb(siv);
+                }
+            }
             siv.pop_layer();
         }
-        EntryType::Command => {
+        EntryType::Command(ref args) => {
             siv.pop_layer();
-            run_command(siv, entry.str.clone());
+            let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+            commands::run(siv, &entry.str, &args);
         }
-    }
-}
-
-fn run_command(siv: &mut Cursive, str: String) {
-    let str = str.as_str();
-    match str {
-        "info" => info(siv).handle(siv),
-        "debug" => debug(siv).handle(siv),
-        "open" => open_project(siv).handle(siv),
-        "save" => save(siv, None).handle(siv),
-        "new" => new_file(siv).handle(siv),
-        "delete" => delete_file(siv).handle(siv),
-        "rename" => rename_file(siv).handle(siv),
-        "quit" => quit(siv).handle(siv),
-        _ => unreachable!(),
+        EntryType::Completion => set_query(siv, &entry.str),
     }
 }
 
 /// Shows all commands
-fn info(siv: &mut Cursive) -> Result<()> {
+pub fn info(siv: &mut Cursive) -> Result<()> {
     if let Some(pos) = siv.screen_mut().find_layer_from_name("info") {
         siv.screen_mut().remove_layer(pos);
     } else {
+        let mut list = ListView::new()
+            // general info
+            .child(
+                "A `*` in the Title indicates that",
+                TextView::new("the current file has been edited"),
+            )
+            .delimiter()
+            // pck info
+            .child("Version", TextView::new(PKG_VERSION))
+            .child("Repository", TextView::new(PKG_REPOSITORY))
+            .child("Authors", TextView::new(PKG_AUTHORS))
+            .child("Description", TextView::new(PKG_DESCRIPTION))
+            .child("License", TextView::new(PKG_LICENSE))
+            .delimiter();
+        // global shortcuts
+        for command in COMMANDS {
+            if let Some(shortcut) = command.shortcut() {
+                list.add_child(command.title, TextView::new(shortcut));
+            }
+        }
+        list.add_delimiter();
+        // quick access commands
+        for command in COMMANDS {
+            list.add_child(command.title, TextView::new(command.id));
+        }
+        list.add_delimiter();
+        // editor
+        let keymap = &config::get().keymap;
+        for &action in Action::ALL {
+            if let Some(event) = keymap.action(action) {
+                list.add_child(action.title(), TextView::new(event_name(&event)));
+            }
+        }
+        list.add_child("Extend Selection", TextView::new("Mouse Drag"));
+
         siv.add_layer(
             Dialog::new()
                 .title(format!("{PKG_NAME} - Info"))
                 .padding_lrtb(1, 1, 1, 0)
                 .dismiss_button("Close")
-                .content(
-                    ListView::new()
-                        // general info
-                        .child(
-                            "A `*` in the Title indicates that",
-                            TextView::new("the current file has been edited"),
-                        )
-                        .delimiter()
-                        // pck info
-                        .child("Version", TextView::new(PKG_VERSION))
-                        .child("Repository", TextView::new(PKG_REPOSITORY))
-                        .child("Authors", TextView::new(PKG_AUTHORS))
-                        .child("Description", TextView::new(PKG_DESCRIPTION))
-                        .child("License", TextView::new(PKG_LICENSE))
-                        .delimiter()
-                        // shortcuts
-                        // global
-                        .child("Open Quick Access", TextView::new("Ctrl + p"))
-                        .child("Close current View", TextView::new("Esc"))
-                        .delimiter()
-                        // quick access commands
-                        .child("Open Debugger", TextView::new("debug"))
-                        .child("Open Infos", TextView::new("info"))
-                        .child("Opening a new File/Project", TextView::new("open"))
-                        .child("Saving the current opened File", TextView::new("save"))
-                        .child("Creating a new File/Directory", TextView::new("new"))
-                        .child("Renaming a File/Directory", TextView::new("rename"))
-                        .child("Deleting a File/Directory", TextView::new("delete"))
-                        .child("Quitting", TextView::new("quit"))
-                        .delimiter()
-                        // editor
-                        .child("Copying Line", TextView::new("Ctrl + c"))
-                        .child("Paste Clipboard", TextView::new("Ctrl + v"))
-                        .child("Cut Line", TextView::new("Ctrl + x"))
-                        .child("Move Line", TextView::new("Shift + Up/Down"))
-                        .child("Move Cursor to EoL", TextView::new("Shift + Left/Right"))
-                        .child("Move Cursor 5x Up", TextView::new("Page Up"))
-                        .child("Move Cursor 5x Down", TextView::new("Page Down"))
-                        .child("Move Cursor to SoF", TextView::new("Shift + Page Up"))
-                        .child("Move Cursor to EoF", TextView::new("Shift + Page Down"))
-                        .child("Ident", TextView::new("Tab"))
-                        .child("Remove Ident", TextView::new("Shift + Tab"))
-                        .scrollable()
-                        .with_name("info"),
-                ),
+                .content(list.scrollable().with_name("info")),
         );
     }
 
@@ -251,7 +431,7 @@ fn info(siv: &mut Cursive) -> Result<()> {
 }
 
 /// Shows debug
-fn debug(siv: &mut Cursive) -> Result<()> {
+pub fn debug(siv: &mut Cursive) -> Result<()> {
     if let Some(pos) = siv.screen_mut().find_layer_from_name("debug") {
         siv.screen_mut().remove_layer(pos);
     } else {
@@ -274,7 +454,7 @@ fn debug(siv: &mut Cursive) -> Result<()> {
 /// Also notable is that this will reload state so the current file tree, the preferred way
 /// to move through all your current opened files without using the file tree is using
 /// `goto` (`Ctrl` + `g`)
-fn open_project(siv: &mut Cursive) -> Result<()> {
+pub fn open_project(siv: &mut Cursive) -> Result<()> {
     if let Some(pos) = siv.screen_mut().find_layer_from_name("open") {
         siv.screen_mut().remove_layer(pos);
         Ok(())
@@ -298,23 +478,12 @@ fn open_project(siv: &mut Cursive) -> Result<()> {
                         })
                         .unwrap();
 
-                    let mut current_file = None;
-                    let project_path = if inc_path.is_file() {
-                        current_file = Some(inc_path.clone());
-                        PathBuf::from(inc_path.parent().unwrap_or(Path::new("/")))
-                    } else if inc_path.is_dir() {
-                        inc_path
-                    } else {
-                        Error::FileSystem("Path doesn't exists".to_string()).to_dialog(siv);
-                        return;
-                    };
-
-                    if let Err(e) = update_ui_state(siv, &project_path, current_file.as_ref()) {
-                        Into::<Error>::into(e).to_dialog(siv);
-                        return;
+                    match open_path(siv, inc_path) {
+                        Ok(()) => {
+                            siv.pop_layer();
+                        }
+                        Err(e) => e.to_dialog(siv),
                     }
-
-                    siv.pop_layer();
                 })
                 .dismiss_button("Cancel")
                 .full_width()
@@ -325,19 +494,40 @@ fn open_project(siv: &mut Cursive) -> Result<()> {
     }
 }
 
+/// Opens the file or directory at `inc_path`, a file's directory becomes the project
+///
+/// The session of the new project is restored, a given file stays the active one.
+pub fn open_path(siv: &mut Cursive, inc_path: PathBuf) -> Result<()> {
+    let mut current_file = None;
+    let project_path = if inc_path.is_file() {
+        current_file = Some(inc_path.clone());
+        PathBuf::from(inc_path.parent().unwrap_or(Path::new("/")))
+    } else if inc_path.is_dir() {
+        inc_path
+    } else {
+        return Err(Error::FileSystem("Path doesn't exists".to_string()));
+    };
+
+    session::save(siv).handle(siv);
+    update_ui_state(siv, &project_path, current_file.as_ref())?;
+    session::restore(siv)
+}
+
 /// Save current progress + Handling Title
 pub fn save(siv: &mut Cursive, other: Option<(&PathBuf, &String)>) -> Result<()> {
     let mut state = siv
         .with_user_data(|state: &mut State| state.clone())
         .unwrap();
 
-    let binding = FileData::default();
-    let content = &state.get_current_file().unwrap_or(&binding).str;
+    let content = state
+        .get_current_file()
+        .map(|file| file.content().to_string())
+        .unwrap_or_default();
 
     let current_file = state
         .current_file
         .as_ref()
-        .map(|current_file| (current_file, content));
+        .map(|current_file| (current_file, &content));
 
     let data = if let Some(other) = other {
         Some(other)
@@ -345,17 +535,39 @@ pub fn save(siv: &mut Cursive, other: Option<(&PathBuf, &String)>) -> Result<()>
         current_file
     };
 
-    if let Some(data) = data {
-        let old_content = fs::read_to_string(data.0)?;
+    if let Some((path, content)) = data {
+        // a recovered file which was deleted is created again
+        let old_content = match fs::read_to_string(path) {
+            Ok(old_content) => Some(old_content),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        // never overwrite changes made by other programs in the meantime
+        if let (Some(file), Some(old_content)) = (state.get_file(path), &old_content) {
+            if file.disk.hash != content_hash(old_content) {
+                return Err(Error::FileSystem(format!(
+                    "\"{}\" changed on disk since it was loaded, reload it or keep your version first",
+                    path.to_string_lossy()
+                )));
+            }
+        }
 
-        if &old_content != data.1 {
+        if old_content.as_ref() != Some(content) {
             // just write when something really changed
-            fs::write(data.0.clone(), data.1)?;
+            fs::write(path, content)?;
         }
 
-        update_title(siv, None, data.0);
+        update_title(siv, false, path);
 
-        state.files_edited.remove(data.0);
+        let path = path.clone();
+        let disk = DiskState::new(&path, content);
+        if let Some(file) = state.files.get_mut(&path) {
+            file.disk = disk;
+            file.ignored_change = None;
+        }
+        swap::remove(&path);
+        state.files_edited.remove(&path);
 
         siv.set_user_data(state);
     }
@@ -363,7 +575,7 @@ pub fn save(siv: &mut Cursive, other: Option<(&PathBuf, &String)>) -> Result<()>
 }
 
 /// Creates a new file/directory
-fn new_file(siv: &mut Cursive) -> Result<()> {
+pub fn new_file(siv: &mut Cursive) -> Result<()> {
     if let Some(pos) = siv.screen_mut().find_layer_from_name("new") {
         siv.screen_mut().remove_layer(pos);
     } else {
@@ -379,60 +591,32 @@ fn new_file(siv: &mut Cursive) -> Result<()> {
                     "new_path".to_string(),
                     false,
                 )?)
-                .button("A File", {
-                    move |siv: &mut Cursive| {
-                        let state = siv
-                            .with_user_data(|state: &mut State| state.clone())
-                            .unwrap();
-                        let new_path = siv
-                            .call_on_name("new_path_edit", |view: &mut EditView| {
-                                PathBuf::from(view.get_content().to_string())
-                            })
-                            .unwrap();
-
-                        if let Err(e) = OpenOptions::new()
-                            .write(true)
-                            .create_new(true)
-                            .open(new_path)
-                        {
-                            Into::<Error>::into(e).to_dialog(siv);
-                            return;
-                        }
-
-                        siv.call_on_name("tree", |tree: &mut TreeView<TreeEntry>| {
-                            load_parent(tree, &state.project_path);
-                        });
+                .button("A File", |siv| {
+                    let new_path = siv
+                        .call_on_name("new_path_edit", |view: &mut EditView| {
+                            PathBuf::from(view.get_content().to_string())
+                        })
+                        .unwrap();
 
-                        siv.pop_layer();
+                    match create_file(siv, &new_path) {
+                        Ok(()) => {
+                            siv.pop_layer();
+                        }
+                        Err(e) => e.to_dialog(siv),
                     }
                 })
-                .button("A Directory", {
-                    move |siv: &mut Cursive| {
-                        let state = siv
-                            .with_user_data(|state: &mut State| state.clone())
-                            .unwrap();
-                        let new_path = siv
-                            .call_on_name("new_path_edit", |view: &mut EditView| {
-                                PathBuf::from(view.get_content().to_string())
-                            })
-                            .unwrap();
-
-                        // Should `crate_dir_all` already catch but it doesn't so checking it here.
-                        if Path::new(&new_path).try_exists().unwrap_or(false) {
-                            Error::FileSystem("File already exists".to_string()).to_dialog(siv);
-                            return;
-                        }
+                .button("A Directory", |siv| {
+                    let new_path = siv
+                        .call_on_name("new_path_edit", |view: &mut EditView| {
+                            PathBuf::from(view.get_content().to_string())
+                        })
+                        .unwrap();
 
-                        if let Err(e) = fs::create_dir_all(new_path) {
-                            Into::<Error>::into(e).to_dialog(siv);
-                            return;
+                    match create_dir(siv, &new_path) {
+                        Ok(()) => {
+                            siv.pop_layer();
                         }
-
-                        siv.call_on_name("tree", |tree: &mut TreeView<TreeEntry>| {
-                            load_parent(tree, &state.project_path);
-                        });
-
-                        siv.pop_layer();
+                        Err(e) => e.to_dialog(siv),
                     }
                 })
                 .dismiss_button("Cancel")
@@ -443,8 +627,39 @@ fn new_file(siv: &mut Cursive) -> Result<()> {
     Ok(())
 }
 
-/// Rename(+move) a file/directory
-fn rename_file(siv: &mut Cursive) -> Result<()> {
+/// Creates a new, empty file at `new_path`
+pub fn create_file(siv: &mut Cursive, new_path: &Path) -> Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(new_path)?;
+    reload_tree(siv);
+    Ok(())
+}
+
+/// Creates a new directory at `new_path`, including its parents
+pub fn create_dir(siv: &mut Cursive, new_path: &Path) -> Result<()> {
+    // Should `crate_dir_all` already catch but it doesn't so checking it here.
+    if new_path.try_exists().unwrap_or(false) {
+        return Err(Error::FileSystem("File already exists".to_string()));
+    }
+    fs::create_dir_all(new_path)?;
+    reload_tree(siv);
+    Ok(())
+}
+
+/// Reloads the file tree, e.g. after files were created
+fn reload_tree(siv: &mut Cursive) {
+    let state = siv
+        .with_user_data(|state: &mut State| state.clone())
+        .unwrap();
+    siv.call_on_name("tree", |tree: &mut TreeView<TreeEntry>| {
+        load_parent(tree, &state.project_path);
+    });
+}
+
+/// Rename(+move) a file/directory, optionally starting with the path to rename
+pub fn rename_file(siv: &mut Cursive, from: Option<PathBuf>) -> Result<()> {
     if let Some(pos) = siv.screen_mut().find_layer_from_name("rename") {
         siv.screen_mut().remove_layer(pos);
     } else {
@@ -459,7 +674,11 @@ fn rename_file(siv: &mut Cursive) -> Result<()> {
             .child(
                 LinearLayout::horizontal()
                     .child(LinearLayout::vertical().child(TextView::new("From")).child(
-                        path_input::new(&state.project_path, "from_rename_path".to_string(), true)?,
+                        path_input::new(
+                            &from.unwrap_or(state.project_path.clone()),
+                            "from_rename_path".to_string(),
+                            true,
+                        )?,
                     ))
                     .child(TextView::new(" "))
                     .child(
@@ -479,9 +698,6 @@ fn rename_file(siv: &mut Cursive) -> Result<()> {
                 .padding_lrtb(1, 1, 1, 0)
                 .content(layout)
                 .button("Confirm", |siv| {
-                    let mut state = siv
-                        .with_user_data(|state: &mut State| state.clone())
-                        .unwrap();
                     let from = siv
                         .call_on_name("from_rename_path_edit", |view: &mut EditView| {
                             PathBuf::from(view.get_content().to_string())
@@ -494,31 +710,12 @@ fn rename_file(siv: &mut Cursive) -> Result<()> {
                         })
                         .unwrap();
 
-                    if !to.exists() {
-                        if let Err(e) = fs::rename(&from, &to) {
-                            Into::<Error>::into(e).to_dialog(siv);
-                            return;
+                    match rename_path(siv, &from, &to) {
+                        Ok(()) => {
+                            siv.pop_layer();
                         }
-                    } else {
-                        Into::<Error>::into(io::Error::new(
-                            io::ErrorKind::AlreadyExists,
-                            "Destination already exists",
-                        ))
-                        .to_dialog(siv);
-                        return;
-                    }
-
-                    state.update_paths_after_rename(&from, &to);
-                    siv.set_user_data(state.clone());
-
-                    if let Err(e) =
-                        update_ui_state(siv, &state.project_path, state.current_file.as_ref())
-                    {
-                        Into::<Error>::into(e).to_dialog(siv);
-                        return;
+                        Err(e) => e.to_dialog(siv),
                     }
-
-                    siv.pop_layer();
                 })
                 .dismiss_button("Cancel")
                 .full_width()
@@ -528,8 +725,59 @@ fn rename_file(siv: &mut Cursive) -> Result<()> {
     Ok(())
 }
 
-/// Delete a file/directory(recursively)
-fn delete_file(siv: &mut Cursive) -> Result<()> {
+/// Renames/moves `from` to `to`, keeping the changes of its open files
+pub fn rename_path(siv: &mut Cursive, from: &Path, to: &Path) -> Result<()> {
+    let mut state = siv
+        .with_user_data(|state: &mut State| state.clone())
+        .unwrap();
+
+    ensure_in_project(from, &state.project_path)?;
+    ensure_in_project(to, &state.project_path)?;
+    if to.exists() {
+        return Err(
+            io::Error::new(io::ErrorKind::AlreadyExists, "Destination already exists").into(),
+        );
+    }
+    fs::rename(from, to)?;
+
+    // The swap files have to follow their buffers.
+    for path in state.files_edited.keys() {
+        if path.starts_with(from) {
+            swap::remove(path);
+        }
+    }
+    state.update_paths_after_rename(from, to);
+    for (path, data) in &state.files {
+        if path.starts_with(to) && state.is_file_edited(path) {
+            swap::update(path, data.content());
+        }
+    }
+    siv.set_user_data(state.clone());
+
+    update_ui_state(siv, &state.project_path, state.current_file.as_ref())
+}
+
+/// Refuses `path` if it's outside of `project_path`
+///
+/// Only its parent has to exist, a symlink itself is inside if its parent is.
+fn ensure_in_project(path: &Path, project_path: &Path) -> Result<()> {
+    let resolved = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+            fs::canonicalize(parent)?.join(name)
+        }
+        _ => fs::canonicalize(path)?,
+    };
+    if !resolved.starts_with(fs::canonicalize(project_path)?) {
+        return Err(Error::Arguments(format!(
+            "\"{}\" is outside of the project",
+            path.to_string_lossy()
+        )));
+    }
+    Ok(())
+}
+
+/// Delete a file/directory(recursively), optionally starting with the path to delete
+pub fn delete_file(siv: &mut Cursive, path: Option<PathBuf>) -> Result<()> {
     if let Some(pos) = siv.screen_mut().find_layer_from_name("delete") {
         siv.screen_mut().remove_layer(pos);
     } else {
@@ -542,7 +790,7 @@ fn delete_file(siv: &mut Cursive) -> Result<()> {
             ))
             .child(TextView::new(" "))
             .child(path_input::new(
-                &state.project_path,
+                &path.unwrap_or(state.project_path.clone()),
                 "delete_path".to_string(),
                 true,
             )?);
@@ -552,53 +800,24 @@ fn delete_file(siv: &mut Cursive) -> Result<()> {
                 .padding_lrtb(1, 1, 1, 0)
                 .content(layout)
                 .button("Confirm", |siv| {
-                    let mut state = siv
-                        .with_user_data(|state: &mut State| state.clone())
-                        .unwrap();
                     let delete_path = siv
                         .call_on_name("delete_path_edit", |view: &mut EditView| {
                             PathBuf::from(view.get_content().to_string())
                         })
                         .unwrap();
 
-                    if delete_path.is_dir() {
-                        if let Err(e) = fs::remove_dir_all(&delete_path) {
-                            Into::<Error>::into(e).to_dialog(siv);
-                            return;
+                    match remove_path(siv, &delete_path) {
+                        Ok(()) => {
+                            siv.pop_layer();
+                        }
+                        Err(e) => {
+                            // the project itself got deleted
+                            if !delete_path.exists() {
+                                siv.pop_layer();
+                            }
+                            e.to_dialog(siv);
                         }
-                    } else if let Err(e) = fs::remove_file(&delete_path) {
-                        Into::<Error>::into(e).to_dialog(siv);
-                        return;
-                    }
-
-                    state.remove(&delete_path);
-
-                    siv.set_user_data(state.clone());
-
-                    if state.project_path == delete_path {
-                        siv.pop_layer();
-                        Into::<Error>::into(io::Error::new(
-                            ErrorKind::NotFound,
-                            "Couldn't find project. It got deleted. Open a new project via the Quick Access view",
-                        ))
-                        .to_dialog(siv);
-                        return;
-                    }
-
-                    let current = if &delete_path
-                        != state.current_file.as_ref().unwrap_or(&PathBuf::default())
-                    {
-                        state.current_file
-                    } else {
-                        None
-                    };
-
-                    if let Err(e) = update_ui_state(siv, &state.project_path, current.as_ref()) {
-                        Into::<Error>::into(e).to_dialog(siv);
-                        return;
                     }
-
-                    siv.pop_layer();
                 })
                 .dismiss_button("Cancel")
                 .full_width()
@@ -608,17 +827,58 @@ fn delete_file(siv: &mut Cursive) -> Result<()> {
     Ok(())
 }
 
+/// Deletes the file/directory at `delete_path` recursively, which has to be in the project
+pub fn remove_path(siv: &mut Cursive, delete_path: &Path) -> Result<()> {
+    let mut state = siv
+        .with_user_data(|state: &mut State| state.clone())
+        .unwrap();
+
+    ensure_in_project(delete_path, &state.project_path)?;
+    if delete_path.is_dir() {
+        fs::remove_dir_all(delete_path)?;
+    } else {
+        fs::remove_file(delete_path)?;
+    }
+
+    for path in state.files_edited.keys() {
+        if path.starts_with(delete_path) {
+            swap::remove(path);
+        }
+    }
+    state.remove(&delete_path.to_path_buf());
+
+    siv.set_user_data(state.clone());
+
+    if state.project_path == delete_path {
+        return Err(io::Error::new(
+            ErrorKind::NotFound,
+            "Couldn't find project. It got deleted. Open a new project via the Quick Access view",
+        )
+        .into());
+    }
+
+    let current = if Some(delete_path) != state.current_file.as_deref() {
+        state.current_file
+    } else {
+        None
+    };
+
+    update_ui_state(siv, &state.project_path, current.as_ref())
+}
+
 /// Quits safely the app
 pub fn quit(siv: &mut Cursive) -> Result<()> {
+    session::save(siv).handle(siv);
+
     let state = siv
         .with_user_data(|state: &mut State| state.clone())
         .unwrap();
 
     let edited_files = state
         .files_edited
-        .into_iter()
-        .filter(|(_, edited)| *edited)
-        .map(|(path, _)| path)
+        .iter()
+        .filter(|(_, edited)| **edited)
+        .map(|(path, _)| path.clone())
         .collect::<Vec<PathBuf>>();
 
     if edited_files.is_empty() {
@@ -641,14 +901,26 @@ pub fn quit(siv: &mut Cursive) -> Result<()> {
                 .title("Unsaved Changes")
                 .content(layout)
                 .button("Yes", move |siv| {
+                    let mut saved = true;
                     for i in &edited_files_for_save {
-                        let binding = &FileData::default();
-                        let content = &state.files.get(i).unwrap_or(binding).str;
-                        save(siv, Some((i, content))).handle(siv);
+                        let content = state
+                            .get_file(i)
+                            .map(|file| file.content().to_string())
+                            .unwrap_or_default();
+                        if let Err(e) = save(siv, Some((i, &content))) {
+                            e.to_dialog(siv);
+                            saved = false;
+                        }
+                    }
+                    // keep running, so refused files aren't lost
+                    if saved {
+                        siv.quit();
                     }
-                    siv.quit();
                 })
-                .button("No", |siv| {
+                .button("No", move |siv| {
+                    for path in &edited_files {
+                        swap::remove(path);
+                    }
                     siv.pop_layer();
                     siv.quit();
                 })
@@ -0,0 +1,226 @@
+use cursive::{
+    event::{Event, Key},
+    view::{Nameable, Resizable},
+    views::{
+        Button, Checkbox, EditView, HideableView, LinearLayout, NamedView, OnEventView, TextView,
+    },
+    Cursive,
+};
+
+use crate::search::{Search, SearchOptions};
+
+use super::edit_area::EditArea;
+
+pub type SearchBar = HideableView<OnEventView<LinearLayout>>;
+
+/// Creates the (hidden) search bar docked below the editor
+///
+/// `Enter`/`Down` jumps to the next, `Shift` + `Enter`/`Up` to the previous match.
+/// `Alt` + `c`, `Alt` + `w` and `Alt` + `r` toggle case sensitivity, whole word and regex matching.
+///
+/// The replace row below is only shown if opened via [`open_replace`], `Enter` replaces the
+/// current match and `Alt` + `a` all of them.
+pub fn new() -> NamedView<SearchBar> {
+    let query = OnEventView::new(
+        EditView::new()
+            .on_edit(|siv, _, _| find(siv))
+            .with_name("search_query"),
+    )
+    .on_event(Key::Enter, |siv| next(siv, true))
+    .on_event(Key::Down, |siv| next(siv, true))
+    .on_event(Event::Shift(Key::Enter), |siv| next(siv, false))
+    .on_event(Key::Up, |siv| next(siv, false));
+
+    let search_row = LinearLayout::horizontal()
+        .child(TextView::new("Find    "))
+        .child(query.full_width())
+        .child(TextView::new("").with_name("search_count").fixed_width(16))
+        .child(
+            Checkbox::labelled("Case")
+                .on_change(|siv, _| find(siv))
+                .with_name("search_case"),
+        )
+        .child(TextView::new(" "))
+        .child(
+            Checkbox::labelled("Word")
+                .on_change(|siv, _| find(siv))
+                .with_name("search_word"),
+        )
+        .child(TextView::new(" "))
+        .child(
+            Checkbox::labelled("Regex")
+                .on_change(|siv, _| find(siv))
+                .with_name("search_regex"),
+        );
+
+    let replace_row = LinearLayout::horizontal()
+        .child(TextView::new("Replace "))
+        .child(
+            EditView::new()
+                .on_submit(|siv, _| replace(siv, false))
+                .with_name("replace_query")
+                .full_width(),
+        )
+        .child(TextView::new("").with_name("replace_count").fixed_width(16))
+        .child(Button::new_raw("[Replace]", |siv| replace(siv, false)))
+        .child(TextView::new(" "))
+        .child(Button::new_raw("[All]", |siv| replace(siv, true)));
+
+    let layout = OnEventView::new(
+        LinearLayout::vertical()
+            .child(search_row)
+            .child(HideableView::new(replace_row).with_name("replace_row")),
+    )
+    .on_pre_event(Key::Esc, close)
+    .on_event(Event::AltChar('c'), |siv| toggle(siv, "search_case"))
+    .on_event(Event::AltChar('w'), |siv| toggle(siv, "search_word"))
+    .on_event(Event::AltChar('r'), |siv| toggle(siv, "search_regex"))
+    .on_event(Event::AltChar('a'), |siv| replace(siv, true));
+
+    HideableView::new(layout).hidden().with_name("search_bar")
+}
+
+/// Shows the search bar and focuses its query, searching for the current query again
+pub fn open(siv: &mut Cursive) {
+    if !show(siv, false) {
+        return;
+    }
+    siv.focus_name("search_query").unwrap();
+    find(siv);
+}
+
+/// Shows the search bar including the replace row
+///
+/// The replacement is focused if there is already a query, otherwise the query.
+pub fn open_replace(siv: &mut Cursive) {
+    if !show(siv, true) {
+        return;
+    }
+    let query = siv
+        .call_on_name("search_query", |view: &mut EditView| view.get_content())
+        .unwrap();
+    if query.is_empty() {
+        siv.focus_name("search_query").unwrap();
+    } else {
+        siv.focus_name("replace_query").unwrap();
+    }
+    find(siv);
+}
+
+/// Shows the search bar if the editor is enabled, returns if it's shown
+fn show(siv: &mut Cursive, replace: bool) -> bool {
+    let enabled = siv
+        .call_on_name("editor", |edit_area: &mut EditArea| edit_area.is_enabled())
+        .unwrap_or(false);
+    if enabled {
+        siv.call_on_name("search_bar", |view: &mut SearchBar| view.unhide());
+        siv.call_on_name("replace_row", |view: &mut HideableView<LinearLayout>| {
+            view.set_visible(replace);
+        });
+    }
+    enabled
+}
+
+/// Hides the search bar, removes the search highlighting and focuses the editor
+pub fn close(siv: &mut Cursive) {
+    siv.call_on_name("search_bar", |view: &mut SearchBar| view.hide());
+    siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        edit_area.set_search(None)
+    });
+    siv.focus_name("editor").unwrap();
+}
+
+/// Searches for the current query and selects the first match from the cursor on
+fn find(siv: &mut Cursive) {
+    let query = siv
+        .call_on_name("search_query", |view: &mut EditView| view.get_content())
+        .unwrap();
+    let options = SearchOptions {
+        case_sensitive: is_checked(siv, "search_case"),
+        whole_word: is_checked(siv, "search_word"),
+        regex: is_checked(siv, "search_regex"),
+    };
+
+    // An incomplete regex is expected while typing, so it's shown in the counter instead of a dialog.
+    let search = Search::new(&query, options);
+    let valid = search.is_ok();
+    if let Some(cb) = siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        edit_area.set_search(search.ok().flatten())
+    }) {
+        cb(siv);
+    }
+
+    update_count(siv);
+    if !valid {
+        siv.call_on_name("search_count", |view: &mut TextView| {
+            view.set_content(" Invalid regex");
+        });
+    }
+}
+
+/// Selects the next or previous match
+fn next(siv: &mut Cursive, forward: bool) {
+    if let Some(cb) = siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        edit_area.search_next(forward)
+    }) {
+        cb(siv);
+    }
+
+    update_count(siv);
+}
+
+/// Replaces the current or all matches with the replacement
+fn replace(siv: &mut Cursive, all: bool) {
+    let replacement = siv
+        .call_on_name("replace_query", |view: &mut EditView| view.get_content())
+        .unwrap();
+    if let Some(cb) = siv.call_on_name("editor", |edit_area: &mut EditArea| {
+        if all {
+            edit_area.replace_all(&replacement)
+        } else {
+            edit_area.replace_next(&replacement)
+        }
+    }) {
+        cb(siv);
+    }
+
+    update_count(siv);
+}
+
+/// Updates the "n of m" match counter and the replace preview
+pub fn update_count(siv: &mut Cursive) {
+    let status = siv
+        .call_on_name("editor", |edit_area: &mut EditArea| {
+            edit_area.search_status()
+        })
+        .flatten();
+
+    let count = match status {
+        Some((_, 0)) => " No results".to_string(),
+        Some((Some(current), total)) => format!(" {} of {total}", current + 1),
+        Some((None, total)) => format!(" {total} matches"),
+        None => String::new(),
+    };
+
+    let replace_count = match status {
+        Some((_, total)) if total > 0 => format!(" {total} to replace"),
+        _ => String::new(),
+    };
+
+    siv.call_on_name("search_count", |view: &mut TextView| {
+        view.set_content(count);
+    });
+    siv.call_on_name("replace_count", |view: &mut TextView| {
+        view.set_content(replace_count);
+    });
+}
+
+fn toggle(siv: &mut Cursive, name: &str) {
+    siv.call_on_name(name, |checkbox: &mut Checkbox| checkbox.toggle());
+    find(siv);
+}
+
+pub fn is_checked(siv: &mut Cursive, name: &str) -> bool {
+    siv.call_on_name(name, |checkbox: &mut Checkbox| checkbox.is_checked())
+        .unwrap_or_default()
+}
@@ -0,0 +1,57 @@
+//! Picking a theme while previewing it
+
+use cursive::{
+    event::Key,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Dialog, OnEventView, SelectView},
+    Cursive,
+};
+
+use crate::{config, error::ResultExt, theme};
+
+/// Shows all themes, the selected one is previewed until another one is chosen or it's cancelled
+pub fn new(siv: &mut Cursive) {
+    if siv.find_name::<Dialog>("theme_picker").is_some() {
+        return;
+    }
+
+    // pick up themes added since the start
+    let themes = theme::reload();
+    let current = config::get().theme.clone();
+
+    let mut select = SelectView::new()
+        .with_all_str(themes.themes.keys())
+        .on_select(|siv, name: &String| preview(siv, name))
+        .on_submit(|siv, name: &String| choose(siv, name));
+    let selected = themes.themes.keys().position(|name| *name == current);
+    select.set_selection(selected.unwrap_or_default());
+
+    siv.add_layer(
+        OnEventView::new(
+            Dialog::around(select.scrollable().max_height(20))
+                .title("Theme")
+                .padding_lrtb(1, 1, 1, 0)
+                .button("Cancel", cancel)
+                .with_name("theme_picker"),
+        )
+        .on_pre_event(Key::Esc, cancel)
+        .min_width(40),
+    );
+}
+
+/// Applies `name` without saving it
+fn preview(siv: &mut Cursive, name: &str) {
+    theme::set(siv, name).handle(siv);
+}
+
+fn choose(siv: &mut Cursive, name: &str) {
+    siv.pop_layer();
+    theme::choose(siv, name).handle(siv);
+}
+
+/// Reverts to the theme of the config
+fn cancel(siv: &mut Cursive) {
+    siv.pop_layer();
+    let current = config::get().theme.clone();
+    theme::set(siv, &current).handle(siv);
+}
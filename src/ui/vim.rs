@@ -0,0 +1,305 @@
+//! Modal editing like in Vim, enabled by the `vim` keymap preset.
+//!
+//! Only the keys of the normal and visual modes are handled here, they are parsed into a
+//! [`Command`] which is run by the `EditArea`. The insert mode uses the regular keybindings.
+
+use cursive::event::{Event, Key};
+
+/// The editing mode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    /// Selecting characters
+    Visual,
+    /// Selecting whole lines
+    VisualLine,
+}
+
+impl Mode {
+    /// The name shown in the title of the editor
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+            Mode::VisualLine => "VISUAL LINE",
+        }
+    }
+
+    pub fn is_visual(self) -> bool {
+        matches!(self, Mode::Visual | Mode::VisualLine)
+    }
+}
+
+/// A movement of the cursor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// Start of the next word (`w`)
+    WordStart,
+    /// End of the word (`e`)
+    WordEnd,
+    /// Start of the previous word (`b`)
+    WordBack,
+    /// First column (`0`)
+    LineStart,
+    /// First non-blank character (`^`)
+    FirstNonBlank,
+    /// End of the line (`$`)
+    LineEnd,
+    /// First line or the line of the count (`gg`)
+    FileStart,
+    /// Last line or the line of the count (`G`)
+    FileEnd,
+    /// The current line and `count - 1` lines below, like in `dd`
+    Lines,
+    /// The bracket matching the one at the cursor (`%`)
+    MatchingBracket,
+}
+
+impl Motion {
+    /// Whether an operator applies to whole lines
+    pub fn is_linewise(self) -> bool {
+        matches!(
+            self,
+            Motion::Up | Motion::Down | Motion::FileStart | Motion::FileEnd | Motion::Lines
+        )
+    }
+
+    /// Whether an operator includes the character at the target
+    pub fn is_inclusive(self) -> bool {
+        matches!(self, Motion::WordEnd | Motion::MatchingBracket)
+    }
+}
+
+/// An operation on the text of a motion or the selection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    /// Copying
+    Yank,
+    /// Deleting and switching to the insert mode
+    Change,
+    Indent,
+    Dedent,
+}
+
+/// Where the insert mode is started
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertAt {
+    /// At the cursor (`i`)
+    Cursor,
+    /// After the cursor (`a`)
+    After,
+    /// At the first non-blank character (`I`)
+    LineStart,
+    /// At the end of the line (`A`)
+    LineEnd,
+    /// In a new line below (`o`)
+    LineBelow,
+    /// In a new line above (`O`)
+    LineAbove,
+}
+
+/// A complete command of the normal or visual mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Moving the cursor `count` times, extending the selection in the visual modes
+    Move(Motion, usize),
+    /// Applying the operator to the text from the cursor to the target of the motion
+    Operate(Operator, Motion, usize),
+    /// Applying the operator to the selection of the visual modes
+    OperateSelection(Operator),
+    Insert(InsertAt),
+    /// Switching to the mode, the normal one ends the visual modes
+    SwitchMode(Mode),
+    /// Pasting after the cursor or below the line (`p`), otherwise before (`P`)
+    Paste {
+        after: bool,
+    },
+    Undo,
+    Redo,
+    HalfPageDown,
+    HalfPageUp,
+    /// Starting a search in the file (`/`)
+    Find,
+    /// Jumping to the next (`n`) or previous (`N`) match of the search
+    SearchNext {
+        forward: bool,
+    },
+    /// Opening the commands of the Quick Access view (`:`)
+    Commands,
+}
+
+/// Outcome of a key in the normal or visual mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parsed {
+    Command(Command),
+    /// The key was consumed without completing a command, e.g. a count
+    Pending,
+    /// The key isn't used by the mode
+    Unknown,
+}
+
+/// Keys of the normal and visual modes typed so far
+#[derive(Clone, Debug, Default)]
+pub struct Vim {
+    pub mode: Mode,
+    count: Option<usize>,
+    operator: Option<Operator>,
+    /// `g` was typed, waiting for the second `g`
+    pending_g: bool,
+}
+
+impl Vim {
+    /// Switches to `mode`, discarding the keys typed so far
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.reset();
+    }
+
+    /// Whether keys of an incomplete command were typed
+    fn is_pending(&self) -> bool {
+        self.count.is_some() || self.operator.is_some() || self.pending_g
+    }
+
+    fn reset(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.pending_g = false;
+    }
+
+    /// Parses the next key of the normal or visual mode
+    pub fn parse(&mut self, event: &Event) -> Parsed {
+        // `Esc` discards the keys typed so far
+        if *event == Event::Key(Key::Esc) && self.is_pending() {
+            self.reset();
+            return Parsed::Pending;
+        }
+        let parsed = self.parse_key(event);
+        if parsed != Parsed::Pending {
+            self.reset();
+        }
+        parsed
+    }
+
+    fn parse_key(&mut self, event: &Event) -> Parsed {
+        let count = self.count.unwrap_or(1);
+        let visual = self.mode.is_visual();
+
+        if self.pending_g {
+            self.pending_g = false;
+            return match event {
+                Event::Char('g') => self.motion(Motion::FileStart, self.count.unwrap_or(0)),
+                _ => Parsed::Unknown,
+            };
+        }
+
+        if let Event::Char(c @ '0'..='9') = *event {
+            let digit = c.to_digit(10).unwrap_or_default() as usize;
+            // a leading `0` is the motion to the first column
+            if digit != 0 || self.count.is_some() {
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10) + digit);
+                return Parsed::Pending;
+            }
+        }
+
+        let motion = match event {
+            Event::Char('h') | Event::Key(Key::Left | Key::Backspace) => Some(Motion::Left),
+            Event::Char('l' | ' ') | Event::Key(Key::Right) => Some(Motion::Right),
+            Event::Char('k') | Event::Key(Key::Up) => Some(Motion::Up),
+            Event::Char('j') | Event::Key(Key::Down | Key::Enter) => Some(Motion::Down),
+            Event::Char('w') => Some(Motion::WordStart),
+            Event::Char('e') => Some(Motion::WordEnd),
+            Event::Char('b') => Some(Motion::WordBack),
+            Event::Char('0') | Event::Key(Key::Home) => Some(Motion::LineStart),
+            Event::Char('^') => Some(Motion::FirstNonBlank),
+            Event::Char('$') | Event::Key(Key::End) => Some(Motion::LineEnd),
+            Event::Char('%') => Some(Motion::MatchingBracket),
+            Event::Char('G') => return self.motion(Motion::FileEnd, self.count.unwrap_or(0)),
+            _ => None,
+        };
+        if let Some(motion) = motion {
+            return self.motion(motion, count);
+        }
+        if *event == Event::Char('g') {
+            self.pending_g = true;
+            return Parsed::Pending;
+        }
+
+        let operator = match event {
+            Event::Char('d') => Some(Operator::Delete),
+            Event::Char('y') => Some(Operator::Yank),
+            Event::Char('c') => Some(Operator::Change),
+            Event::Char('>') => Some(Operator::Indent),
+            Event::Char('<') => Some(Operator::Dedent),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            if visual {
+                return Parsed::Command(Command::OperateSelection(operator));
+            }
+            // doubling the operator applies it to lines, like `dd`
+            if self.operator == Some(operator) {
+                return Parsed::Command(Command::Operate(operator, Motion::Lines, count));
+            }
+            if self.operator.is_none() {
+                self.operator = Some(operator);
+                return Parsed::Pending;
+            }
+            return Parsed::Unknown;
+        }
+        if self.operator.is_some() {
+            return Parsed::Unknown;
+        }
+
+        let command = match event {
+            Event::Char('x') | Event::Key(Key::Del) if visual => {
+                Command::OperateSelection(Operator::Delete)
+            }
+            Event::Char('x') | Event::Key(Key::Del) => {
+                Command::Operate(Operator::Delete, Motion::Right, count)
+            }
+            Event::Char('X') => Command::Operate(Operator::Delete, Motion::Left, count),
+            Event::Char('D') => Command::Operate(Operator::Delete, Motion::LineEnd, 1),
+            Event::Char('C') => Command::Operate(Operator::Change, Motion::LineEnd, 1),
+            Event::Char('Y') => Command::Operate(Operator::Yank, Motion::Lines, count),
+            Event::Char('p') => Command::Paste { after: true },
+            Event::Char('P') => Command::Paste { after: false },
+            Event::Char('u') => Command::Undo,
+            Event::CtrlChar('r') => Command::Redo,
+            Event::CtrlChar('d') => Command::HalfPageDown,
+            Event::CtrlChar('u') => Command::HalfPageUp,
+            Event::Char('/') => Command::Find,
+            Event::Char('n') => Command::SearchNext { forward: true },
+            Event::Char('N') => Command::SearchNext { forward: false },
+            Event::Char(':') => Command::Commands,
+            Event::Char('v') if self.mode == Mode::Visual => Command::SwitchMode(Mode::Normal),
+            Event::Char('v') => Command::SwitchMode(Mode::Visual),
+            Event::Char('V') if self.mode == Mode::VisualLine => Command::SwitchMode(Mode::Normal),
+            Event::Char('V') => Command::SwitchMode(Mode::VisualLine),
+            Event::Key(Key::Esc) if visual => Command::SwitchMode(Mode::Normal),
+            Event::Char('i') if !visual => Command::Insert(InsertAt::Cursor),
+            Event::Char('a') if !visual => Command::Insert(InsertAt::After),
+            Event::Char('I') if !visual => Command::Insert(InsertAt::LineStart),
+            Event::Char('A') if !visual => Command::Insert(InsertAt::LineEnd),
+            Event::Char('o') if !visual => Command::Insert(InsertAt::LineBelow),
+            Event::Char('O') if !visual => Command::Insert(InsertAt::LineAbove),
+            _ => return Parsed::Unknown,
+        };
+        Parsed::Command(command)
+    }
+
+    /// The motion, applied to the pending operator if there is one
+    fn motion(&self, motion: Motion, count: usize) -> Parsed {
+        Parsed::Command(match self.operator {
+            Some(operator) => Command::Operate(operator, motion, count),
+            None => Command::Move(motion, count),
+        })
+    }
+}
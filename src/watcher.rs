@@ -0,0 +1,187 @@
+//! Detecting changes of open files made by other programs.
+//!
+//! The modification time of every open file is polled regularly, its content is only read and
+//! hashed if that changed. Clean buffers are reloaded silently, for buffers with unsaved changes
+//! the user decides whether to reload or keep them.
+//! The file index of the Quick Access view is rebuilt when files were added or removed.
+
+use std::{fs, path::PathBuf, thread, time::Duration};
+
+use cursive::{
+    view::Resizable,
+    views::{Dialog, LinearLayout, TextView},
+    Cursive,
+};
+
+use crate::{
+    app::{content_hash, modified, DiskState, State},
+    error::{Result, ResultExt},
+    swap,
+    ui::{diff, open_file, quick_access},
+};
+
+/// Interval in which the open files are checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A new version of an open file on disk
+#[derive(Clone, Debug)]
+struct Change {
+    path: PathBuf,
+    content: String,
+    disk: DiskState,
+}
+
+/// Starts checking the open files and the file index for changes in the background
+pub fn start(siv: &mut Cursive) {
+    let cb_sink = siv.cb_sink().clone();
+    let index = siv
+        .with_user_data(|state: &mut State| state.index.clone())
+        .unwrap_or_default();
+    thread::spawn(move || loop {
+        thread::sleep(WATCH_INTERVAL);
+        let refresh_sink = cb_sink.clone();
+        index.update(move || {
+            refresh_sink.send(Box::new(quick_access::refresh)).ok();
+        });
+        if cb_sink.send(Box::new(check)).is_err() {
+            break;
+        }
+    });
+}
+
+/// Reloads clean buffers that changed on disk and asks about edited ones
+fn check(siv: &mut Cursive) {
+    let files = siv
+        .with_user_data(|state: &mut State| {
+            state
+                .files
+                .iter()
+                .map(|(path, file)| (path.clone(), file.disk.modified))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut changes = Vec::new();
+    for (path, known) in files {
+        let modified = modified(&path);
+        if modified == known {
+            continue;
+        }
+        // Deleted or unreadable files are kept as they are.
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        changes.push(Change {
+            path,
+            disk: DiskState {
+                modified,
+                hash: content_hash(&content),
+            },
+            content,
+        });
+    }
+
+    for change in changes {
+        let Some((hash, edited, ignored_change)) = siv
+            .with_user_data(|state: &mut State| {
+                let file = state.files.get(&change.path)?;
+                let edited = state.is_file_edited(&change.path);
+                Some((file.disk.hash, edited, file.ignored_change))
+            })
+            .flatten()
+        else {
+            continue;
+        };
+        if change.disk.hash == hash {
+            // only touched, the content is the same
+            siv.with_user_data(|state: &mut State| {
+                if let Some(file) = state.files.get_mut(&change.path) {
+                    file.disk = change.disk;
+                }
+            });
+        } else if !edited {
+            reload(siv, &change).handle(siv);
+        } else if ignored_change != Some(change.disk.hash) {
+            siv.with_user_data(|state: &mut State| {
+                if let Some(file) = state.files.get_mut(&change.path) {
+                    file.ignored_change = Some(change.disk.hash);
+                }
+            });
+            prompt(siv, change, false);
+        }
+    }
+}
+
+/// Asks whether to reload the edited buffer or to keep it, optionally showing a diff
+fn prompt(siv: &mut Cursive, change: Change, show_diff: bool) {
+    let mut layout = LinearLayout::vertical().child(TextView::new(format!(
+        "\"{}\" changed on disk, but has unsaved changes. Reload it and lose them?",
+        change.path.to_string_lossy()
+    )));
+    if show_diff {
+        let content = siv
+            .with_user_data(|state: &mut State| {
+                state
+                    .get_file(&change.path)
+                    .map(|file| file.content().to_string())
+            })
+            .flatten()
+            .unwrap_or_default();
+        layout.add_child(TextView::new(" "));
+        layout.add_child(diff::new(&content, &change.content));
+    }
+
+    let mut dialog = Dialog::new()
+        .title("File Changed")
+        .padding_lrtb(1, 1, 1, 0)
+        .content(layout);
+    let reload_change = change.clone();
+    dialog.add_button("Reload", move |siv| {
+        siv.pop_layer();
+        reload(siv, &reload_change).handle(siv);
+    });
+    let keep_change = change.clone();
+    dialog.add_button("Keep", move |siv| {
+        siv.pop_layer();
+        keep(siv, &keep_change);
+    });
+    if !show_diff {
+        dialog.add_button("Diff", move |siv| {
+            siv.pop_layer();
+            prompt(siv, change.clone(), true);
+        });
+    }
+
+    siv.add_layer(dialog.full_width());
+}
+
+/// Replaces the buffer with the version on disk, which can be undone
+fn reload(siv: &mut Cursive, change: &Change) -> Result<()> {
+    let is_current = siv.with_user_data(|state: &mut State| {
+        let file = state.files.get_mut(&change.path)?;
+        file.set_content(change.content.clone());
+        file.disk = change.disk;
+        file.ignored_change = None;
+        state.files_edited.remove(&change.path);
+        Some(state.current_file.as_ref() == Some(&change.path))
+    });
+    let Some(is_current) = is_current.flatten() else {
+        return Ok(());
+    };
+
+    swap::remove(&change.path);
+    if is_current {
+        open_file(siv, &change.path)?;
+    }
+    Ok(())
+}
+
+/// Keeps the edited buffer, saving it will overwrite the version on disk
+fn keep(siv: &mut Cursive, change: &Change) {
+    siv.with_user_data(|state: &mut State| {
+        if let Some(file) = state.files.get_mut(&change.path) {
+            file.disk = change.disk;
+            file.ignored_change = None;
+        }
+    });
+}
@@ -0,0 +1,327 @@
+//! Soft wrapping of long lines into rows of the editor's width.
+//!
+//! Lines are broken after whitespace, words longer than a row between graphemes. The rows
+//! continuing a line are indented a bit more than the line itself. Wrapped lines are cached,
+//! after an edit only the lines from the edited one onwards have to be wrapped again, or only
+//! the edited line if no lines were added or removed.
+
+use std::{cmp::min, mem, ops::Range};
+
+use ropey::Rope;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::ui::edit_area::grapheme_width;
+
+/// Columns the rows continuing a line are indented by, in addition to the line's indentation.
+const CONTINUATION_INDENT: usize = 2;
+
+/// The rows of a single line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrappedLine {
+    /// Character offsets in the line at which its rows start, the first one is always `0`
+    pub starts: Vec<usize>,
+    /// Columns the rows continuing the line are indented by
+    pub indent: usize,
+}
+
+impl WrappedLine {
+    /// Wraps `line` into rows of at most `width` columns, whitespace may exceed them.
+    pub fn new(line: &str, width: usize, tab_width: usize) -> Self {
+        let width = width.max(1);
+        let graphemes = line.graphemes(true).collect::<Vec<_>>();
+
+        let indentation = graphemes
+            .iter()
+            .take_while(|g| **g == " " || **g == "\t")
+            .fold(0, |x, g| x + grapheme_width(g, x, tab_width));
+        // at least half of a row is left for the text
+        let indent = Some(indentation + CONTINUATION_INDENT).filter(|indent| indent * 2 <= width);
+        let indent = indent.unwrap_or_default();
+
+        let mut starts = vec![0];
+        // the grapheme index and character offset of the current grapheme
+        let (mut i, mut offset) = (0, 0);
+        let mut row_start = 0;
+        let mut x = 0;
+        // where the row may be broken, behind whitespace following some text
+        let mut break_at = None;
+        let mut has_text = false;
+        while let Some(&grapheme) = graphemes.get(i) {
+            let blank = grapheme.chars().all(char::is_whitespace);
+            let grapheme_width = grapheme_width(grapheme, x, tab_width);
+            if x + grapheme_width > width && i > row_start && !blank {
+                (i, offset) = break_at.take().unwrap_or((i, offset));
+                starts.push(offset);
+                row_start = i;
+                x = indent;
+                continue;
+            }
+            x += grapheme_width;
+            offset += grapheme.chars().count();
+            i += 1;
+            has_text |= !blank;
+            if blank && has_text {
+                break_at = Some((i, offset));
+            }
+        }
+        Self { starts, indent }
+    }
+
+    /// Amount of rows
+    pub fn rows(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// The row containing the character offset of the line
+    pub fn row_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// Columns `row` is indented by
+    pub fn row_indent(&self, row: usize) -> usize {
+        if row == 0 {
+            0
+        } else {
+            self.indent
+        }
+    }
+
+    /// Character offsets of `row` in the line, which is `len` characters long
+    pub fn row_range(&self, row: usize, len: usize) -> Range<usize> {
+        self.starts[row]..self.starts.get(row + 1).copied().unwrap_or(len)
+    }
+}
+
+/// The cached wrapping of a buffer
+#[derive(Debug, Default)]
+pub struct Wrap {
+    /// Columns of a row, without its indentation
+    width: usize,
+    tab_width: usize,
+    /// Wrapped lines, always starting from the first one
+    lines: Vec<WrappedLine>,
+    /// The first row of every wrapped line
+    first_rows: Vec<usize>,
+    /// Lines edited without adding or removing lines, which have to be wrapped again
+    edited: Vec<usize>,
+}
+
+impl Wrap {
+    /// Sets the width of the rows, wrapping all lines again if it changed
+    pub fn set_width(&mut self, width: usize, tab_width: usize) {
+        if (width, tab_width) != (self.width, self.tab_width) {
+            self.width = width;
+            self.tab_width = tab_width;
+            self.invalidate(0);
+        }
+    }
+
+    /// Discards the wrapping from the edited `line` onwards.
+    pub fn invalidate(&mut self, line: usize) {
+        self.lines.truncate(line);
+        self.first_rows.truncate(line);
+    }
+
+    /// Wraps the edited `line` again on the next access, the edit didn't add or remove lines.
+    ///
+    /// The following lines are kept, their rows are only shifted if the amount of rows of the
+    /// edited line changed.
+    pub fn invalidate_line(&mut self, line: usize) {
+        if line < self.lines.len() && !self.edited.contains(&line) {
+            self.edited.push(line);
+        }
+    }
+
+    /// Wraps the lines of `content` up to `line` which aren't cached yet.
+    fn wrap_to(&mut self, content: &Rope, line: usize) {
+        self.rewrap_edited(content);
+        let line = min(line, content.len_lines() - 1);
+        for i in self.lines.len()..=line {
+            let first_row = self.rows_before(i);
+            self.lines.push(self.wrap_line(content, i));
+            self.first_rows.push(first_row);
+        }
+    }
+
+    /// Wraps the edited lines again, shifting the rows of the following lines.
+    fn rewrap_edited(&mut self, content: &Rope) {
+        for line in mem::take(&mut self.edited) {
+            // discarded since it was edited
+            if line >= self.lines.len() {
+                continue;
+            }
+            let wrapped = self.wrap_line(content, line);
+            let shift = wrapped.rows() as isize - self.lines[line].rows() as isize;
+            self.lines[line] = wrapped;
+            if shift != 0 {
+                for first_row in &mut self.first_rows[line + 1..] {
+                    *first_row = first_row.wrapping_add_signed(shift);
+                }
+            }
+        }
+    }
+
+    fn wrap_line(&self, content: &Rope, line: usize) -> WrappedLine {
+        let text = content.line(line).to_string();
+        let text = text.trim_end_matches(['\n', '\r']);
+        WrappedLine::new(text, self.width, self.tab_width)
+    }
+
+    /// Amount of rows before `line`, which is at most the first line which isn't cached
+    fn rows_before(&self, line: usize) -> usize {
+        match line.checked_sub(1) {
+            Some(last) => self.first_rows[last] + self.lines[last].rows(),
+            None => 0,
+        }
+    }
+
+    /// The wrapped `line` of `content`
+    pub fn line(&mut self, content: &Rope, line: usize) -> &WrappedLine {
+        self.wrap_to(content, line);
+        &self.lines[line]
+    }
+
+    /// Amount of rows of all lines
+    pub fn rows(&mut self, content: &Rope) -> usize {
+        let lines = content.len_lines();
+        self.wrap_to(content, lines - 1);
+        self.rows_before(lines)
+    }
+
+    /// The line at `row` and the row in it, clamped to the last row
+    pub fn line_at(&mut self, content: &Rope, row: usize) -> (usize, usize) {
+        self.wrap_to(content, content.len_lines() - 1);
+        let line = self.first_rows.partition_point(|&first| first <= row) - 1;
+        let rows = self.lines[line].rows();
+        (line, min(row - self.first_rows[line], rows - 1))
+    }
+
+    /// The row and column of `char_offset` on screen
+    pub fn position(&mut self, content: &Rope, char_offset: usize) -> (usize, usize) {
+        let line = content.char_to_line(char_offset);
+        let offset = char_offset - content.line_to_char(line);
+        let text = content.line(line).to_string();
+        let tab_width = self.tab_width;
+        let wrapped = self.line(content, line);
+
+        let row = wrapped.row_of(offset);
+        let start = wrapped.starts[row];
+        let column = chars(&text, start..offset)
+            .graphemes(true)
+            .fold(wrapped.row_indent(row), |x, grapheme| {
+                x + grapheme_width(grapheme, x, tab_width)
+            });
+        (self.first_rows[line] + row, column)
+    }
+
+    /// The character offset at `column` of `row`, clamped to the row
+    ///
+    /// A row continued by the next one ends before its last grapheme, as its end is the start
+    /// of the next row.
+    pub fn offset_at(&mut self, content: &Rope, row: usize, column: usize) -> usize {
+        let (line, row) = self.line_at(content, row);
+        let line_start = content.line_to_char(line);
+        let text = content.line(line).to_string();
+        let len = text.trim_end_matches(['\n', '\r']).chars().count();
+        let wrapped = &self.lines[line];
+
+        let range = wrapped.row_range(row, len);
+        let continued = row + 1 < wrapped.rows();
+        let mut x = wrapped.row_indent(row);
+        let mut offset = range.start;
+        for grapheme in chars(&text, range.clone()).graphemes(true) {
+            let next = offset + grapheme.chars().count();
+            x += grapheme_width(grapheme, x, self.tab_width);
+            if x > column || (continued && next == range.end) {
+                break;
+            }
+            offset = next;
+        }
+        line_start + offset
+    }
+}
+
+/// The characters of `range` in `s`
+fn chars(s: &str, range: Range<usize>) -> &str {
+    let byte = |char_offset| {
+        s.char_indices()
+            .nth(char_offset)
+            .map_or(s.len(), |(i, _)| i)
+    };
+    &s[byte(range.start)..byte(range.end)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starts(line: &str, width: usize) -> Vec<usize> {
+        WrappedLine::new(line, width, 4).starts
+    }
+
+    fn wrap_of_width(width: usize) -> Wrap {
+        let mut wrap = Wrap::default();
+        wrap.set_width(width, 4);
+        wrap
+    }
+
+    #[test]
+    fn break_after_whitespace() {
+        assert_eq!(starts("aaa bbb ccc", 11), [0]);
+        // trailing whitespace may exceed the row
+        assert_eq!(starts("aaa bbb ccc", 7), [0, 8]);
+        assert_eq!(starts("aaa bbb ccc", 6), [0, 4, 8]);
+        // leading whitespace isn't a place to break
+        assert_eq!(starts("  aa bb cc", 8), [0, 8]);
+    }
+
+    #[test]
+    fn break_long_words() {
+        assert_eq!(starts("abcdefgh", 4), [0, 4, 6]);
+        // wide characters aren't split, an emoji family is a single grapheme
+        assert_eq!(starts("漢漢漢", 5), [0, 2]);
+        assert_eq!(starts("a👨‍👩‍👧b", 2), [0, 1, 6]);
+    }
+
+    #[test]
+    fn continuation_indent() {
+        assert_eq!(WrappedLine::new("  aa bb cc", 8, 4).indent, 4);
+        assert_eq!(WrappedLine::new("\taa bb cc", 12, 4).indent, 6);
+        // at least half of the row is kept for the text
+        assert_eq!(WrappedLine::new("    aa bb cc", 10, 4).indent, 0);
+    }
+
+    #[test]
+    fn rows_and_positions() {
+        let content = Rope::from_str("aaa bbb ccc\nx\n漢漢漢");
+        let mut wrap = wrap_of_width(6);
+        assert_eq!(wrap.rows(&content), 5);
+        assert_eq!(wrap.line_at(&content, 3), (1, 0));
+        assert_eq!(wrap.line_at(&content, 100), (2, 0));
+        // the continued row is indented
+        assert_eq!(wrap.position(&content, 8), (2, 2));
+        assert_eq!(wrap.position(&content, 16), (4, 4));
+        assert_eq!(wrap.offset_at(&content, 2, 3), 9);
+        // a continued row ends before the start of the next one
+        assert_eq!(wrap.offset_at(&content, 0, 10), 3);
+        // the column of a wide character is before it
+        assert_eq!(wrap.offset_at(&content, 4, 3), 15);
+    }
+
+    #[test]
+    fn rewrap_edited_line() {
+        let mut content = Rope::from_str("aaa bbb ccc\nx\n漢漢漢\nlast");
+        let mut wrap = wrap_of_width(6);
+        assert_eq!(wrap.rows(&content), 6);
+
+        content.insert(13, " y z w");
+        wrap.invalidate_line(1);
+        assert_eq!(wrap.rows(&content), 7);
+        assert_eq!(wrap.line_at(&content, 6), (3, 0));
+
+        let mut fresh = wrap_of_width(6);
+        for row in 0..7 {
+            assert_eq!(wrap.line_at(&content, row), fresh.line_at(&content, row));
+        }
+    }
+}